@@ -0,0 +1,136 @@
+//! Frame pointer based stack unwinding.
+//!
+//! Every function stores a frame record `(x29, x30)` on the stack and points
+//! x29 at it, so the records form a linked list from the innermost frame outwards.
+
+use core::{arch::asm, fmt, iter, ptr::addr_of};
+
+use log::error;
+use symbol_table::SymbolTable;
+
+use crate::{
+    aarch64::mmu::KERNEL_VIRTUAL_MEM_SPACE, configuration::memory_mapping::el1_stack_range,
+};
+
+/// Upper bound of frames to walk, in case the chain is corrupted.
+pub const MAX_DEPTH: usize = 32;
+
+/// Bytes reserved for the symbol table, see `tools/embed_symbols.sh`.
+///
+/// Debug builds carry far more symbols. `NOVA_SYMBOL_TABLE_SIZE` overrides the
+/// size at build time, if the table still doesn't fit.
+const SYMBOL_TABLE_SIZE: usize = match option_env!("NOVA_SYMBOL_TABLE_SIZE") {
+    Some(size) => parse_size(size),
+    None if cfg!(debug_assertions) => 1024 * 1024,
+    None => 256 * 1024,
+};
+
+/// Parses a decimal byte count at compile time.
+const fn parse_size(size: &str) -> usize {
+    let digits = size.as_bytes();
+    assert!(!digits.is_empty(), "NOVA_SYMBOL_TABLE_SIZE is empty");
+
+    let mut value = 0;
+    let mut index = 0;
+    while index < digits.len() {
+        let digit = digits[index];
+        assert!(
+            digit.is_ascii_digit(),
+            "NOVA_SYMBOL_TABLE_SIZE must be a decimal byte count"
+        );
+        value = value * 10 + (digit - b'0') as usize;
+        index += 1;
+    }
+    // The table is placed 8-byte aligned.
+    value.next_multiple_of(8)
+}
+
+/// Space for the symbol table, which is written into the image after linking.
+#[link_section = ".symbol_table"]
+#[used]
+static SYMBOL_TABLE_SPACE: [u8; SYMBOL_TABLE_SIZE] = [0; SYMBOL_TABLE_SIZE];
+
+extern "C" {
+    static __symbol_table_start: u8;
+    static __symbol_table_end: u8;
+}
+
+/// Iterator over the return addresses of the frame records starting at `frame_pointer`.
+pub struct Backtrace {
+    frame_pointer: usize,
+    depth: usize,
+}
+
+impl Backtrace {
+    pub fn new(frame_pointer: usize) -> Self {
+        Self {
+            frame_pointer,
+            depth: 0,
+        }
+    }
+
+    /// Starts at the frame record of the calling function.
+    #[inline(always)]
+    pub fn current() -> Self {
+        let frame_pointer: usize;
+        unsafe { asm!("mov {}, x29", out(reg) frame_pointer) };
+        Self::new(frame_pointer)
+    }
+}
+
+impl Iterator for Backtrace {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame_pointer = self.frame_pointer;
+        if self.depth >= MAX_DEPTH
+            || !frame_pointer.is_multiple_of(8)
+            || !el1_stack_range().contains(&frame_pointer)
+        {
+            return None;
+        }
+
+        let record = frame_pointer as *const usize;
+        let (next, return_address) = unsafe { (*record, *record.add(1)) };
+
+        // Records of callers are always further up the stack.
+        self.frame_pointer = if next > frame_pointer { next } else { 0 };
+        self.depth += 1;
+
+        (return_address != 0).then_some(return_address)
+    }
+}
+
+/// Code address, displayed together with the function containing it.
+pub struct Location(pub usize);
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#018x}", self.0)?;
+
+        let address = (self.0 & !KERNEL_VIRTUAL_MEM_SPACE) as u64;
+        match kernel_symbols().and_then(|symbols| symbols.lookup(address)) {
+            Some(symbol) => write!(f, " {}", symbol),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Symbol table embedded into the image, `None` if it hasn't been embedded.
+fn kernel_symbols() -> Option<SymbolTable<'static>> {
+    let start = addr_of!(__symbol_table_start);
+    let end = addr_of!(__symbol_table_end);
+
+    let data = unsafe { core::slice::from_raw_parts(start, end as usize - start as usize) };
+    SymbolTable::parse(data)
+}
+
+/// Logs the program counter `pc` followed by the return addresses of the
+/// frame records starting at `frame_pointer`.
+pub fn log_backtrace(pc: usize, frame_pointer: usize) {
+    error!("Backtrace:");
+    let frames = iter::once(pc).chain(Backtrace::new(frame_pointer));
+    for (depth, address) in frames.enumerate() {
+        error!("  #{:<2} {}", depth, Location(address));
+    }
+}
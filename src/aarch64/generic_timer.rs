@@ -0,0 +1,121 @@
+//! ARM generic timer, the per-core non-secure physical timer of EL1.
+//!
+//! Each core programs its own timer for a periodic tick. The timer IRQ reaches
+//! `rust_irq_handler` through the core-local interrupt controller.
+
+use core::{
+    arch::asm,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::{
+    aarch64::registers::{current_core, read_cntfrq_el0},
+    pi3::local_interrupt_controller::{disable_physical_timer_irq, enable_physical_timer_irq},
+    smp::CORE_COUNT,
+    sync::{IrqSpinLock, PerCpu},
+};
+
+/// CNTP_CTL_EL0.ENABLE
+const CNTP_CTL_ENABLE: u64 = 1 << 0;
+
+/// CNTKCTL_EL1.EVNTEN
+const CNTKCTL_EVNTEN: u64 = 1 << 2;
+/// Position of CNTKCTL_EL1.EVNTI, the counter bit triggering the event stream.
+const CNTKCTL_EVNTI_SHIFT: u64 = 4;
+
+/// Counter ticks between two timer interrupts of each core, `0` if the tick is stopped.
+static TICK_INTERVAL: PerCpu<AtomicU64> = PerCpu::new([const { AtomicU64::new(0) }; CORE_COUNT]);
+/// Ticks elapsed on each core.
+static TICKS: PerCpu<AtomicU64> = PerCpu::new([const { AtomicU64::new(0) }; CORE_COUNT]);
+/// Called on every tick of every core.
+static TICK_HANDLER: IrqSpinLock<Option<fn()>> = IrqSpinLock::new(None);
+
+/// Frequency of the system counter in Hz.
+pub fn frequency() -> u64 {
+    read_cntfrq_el0()
+}
+
+/// Current value of the system counter.
+pub fn counter() -> u64 {
+    let count: u64;
+    unsafe { asm!("isb", "mrs {}, CNTPCT_EL0", out(reg) count) };
+    count
+}
+
+/// Starts a tick of `hz` interrupts per second on the calling core.
+pub fn start_tick(hz: u64) {
+    let core = current_core();
+    let interval = (frequency() / hz.max(1)).max(1);
+    TICK_INTERVAL.get().store(interval, Ordering::Relaxed);
+
+    set_timer_value(interval);
+    unsafe { asm!("msr CNTP_CTL_EL0, {}", "isb", in(reg) CNTP_CTL_ENABLE) };
+    enable_physical_timer_irq(core);
+}
+
+/// Generates a `wfe` wakeup on the calling core about `hz` times per second,
+/// which bounds every wait for an event.
+pub fn enable_event_stream(hz: u64) {
+    let mut control: u64;
+    unsafe { asm!("mrs {}, CNTKCTL_EL1", out(reg) control) };
+    control = with_event_stream(control, hz);
+    unsafe { asm!("msr CNTKCTL_EL1, {}", "isb", in(reg) control) };
+}
+
+/// Like [`enable_event_stream`], for EL2 before the core enters EL1.
+pub fn enable_event_stream_el2(hz: u64) {
+    let mut control: u64;
+    unsafe { asm!("mrs {}, CNTHCTL_EL2", out(reg) control) };
+    control = with_event_stream(control, hz);
+    unsafe { asm!("msr CNTHCTL_EL2, {}", "isb", in(reg) control) };
+}
+
+/// Sets the event stream bits of CNTKCTL_EL1 or CNTHCTL_EL2, which share
+/// their layout.
+fn with_event_stream(control: u64, hz: u64) -> u64 {
+    // An event is generated every second transition of the selected bit.
+    let period = (frequency() / hz.max(1)).max(2);
+    let bit = (period.ilog2() - 1).min(15) as u64;
+    control & !(0xF << CNTKCTL_EVNTI_SHIFT) | CNTKCTL_EVNTEN | bit << CNTKCTL_EVNTI_SHIFT
+}
+
+/// Stops the tick of the calling core.
+pub fn stop_tick() {
+    let core = current_core();
+    disable_physical_timer_irq(core);
+    unsafe { asm!("msr CNTP_CTL_EL0, {}", "isb", in(reg) 0u64) };
+    TICK_INTERVAL.get().store(0, Ordering::Relaxed);
+}
+
+/// Ticks elapsed on the calling core.
+pub fn ticks() -> u64 {
+    TICKS.get().load(Ordering::Relaxed)
+}
+
+/// Registers `handler` to be called on every tick, replacing the previous one.
+pub fn set_tick_handler(handler: fn()) {
+    *TICK_HANDLER.lock() = Some(handler);
+}
+
+/// Handles the timer interrupt of the calling core and re-arms its timer.
+pub fn handle_tick() {
+    let interval = TICK_INTERVAL.get().load(Ordering::Relaxed);
+    if interval == 0 {
+        // The IRQ is level-triggered, stop the timer instead of re-arming it.
+        stop_tick();
+        return;
+    }
+
+    set_timer_value(interval);
+    TICKS.get().fetch_add(1, Ordering::Relaxed);
+
+    let handler = *TICK_HANDLER.lock();
+    if let Some(handler) = handler {
+        handler();
+    }
+}
+
+/// Fires the timer after `ticks` counter ticks, this also clears a pending interrupt.
+fn set_timer_value(ticks: u64) {
+    unsafe { asm!("msr CNTP_TVAL_EL0, {}", "isb", in(reg) ticks) };
+}
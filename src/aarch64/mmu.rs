@@ -1,11 +1,14 @@
-use core::mem::size_of;
+use core::{arch::asm, mem::size_of};
 use nova_error::NovaError;
 
 use crate::{
     aarch64::mmu::physical_mapping::{
-        reserve_block, reserve_block_explicit, reserve_page, reserve_page_explicit,
+        acquire_frame_reference, free_block, free_page, release_frame_reference, reserve_block,
+        reserve_block_explicit, reserve_page, reserve_page_explicit, set_frame_references,
+        try_reserve_page,
     },
     get_current_el,
+    sync::ReentrantIrqSpinLock,
 };
 
 const BLOCK: u64 = 0b01;
@@ -21,6 +24,8 @@ pub const WRITABLE: u64 = 0 << 7;
 pub const READ_ONLY: u64 = 1 << 7;
 
 const ACCESS_FLAG: u64 = 1 << 10;
+/// Tag the TLB entries of a page with the ASID of the address space.
+pub const NON_GLOBAL: u64 = 1 << 11;
 const INNER_SHAREABILITY: u64 = 0b11 << 8;
 
 pub const NORMAL_MEM: u64 = 0 << 2;
@@ -94,9 +99,22 @@ impl TableEntry {
     }
 
     #[inline]
-    fn address(self) -> PhysAddr {
+    pub fn address(self) -> PhysAddr {
         self.value as usize & 0x0000_FFFF_FFFF_F000
     }
+
+    /// Flags of a page descriptor, as passed to [`map_page`].
+    pub fn page_flags(self) -> u64 {
+        self.value & !(0x0000_FFFF_FFFF_F000 | PAGE | ACCESS_FLAG | INNER_SHAREABILITY)
+    }
+
+    pub fn is_read_only(self) -> bool {
+        self.value & READ_ONLY != 0
+    }
+
+    pub fn set_read_only(&mut self) {
+        self.value |= READ_ONLY;
+    }
 }
 
 pub enum PhysSource {
@@ -124,6 +142,13 @@ pub static mut TRANSLATIONTABLE_TTBR0: PageTable = PageTable([TableEntry { value
 #[no_mangle]
 pub static mut TRANSLATIONTABLE_TTBR1: PageTable = PageTable([TableEntry { value: 0 }; 512]);
 
+/// Serializes changes to the translation tables.
+///
+/// The root tables stay at their symbols, which the boot code loads into the
+/// TTBRs. Table walks allocate tables through [`allocate_frame`], which maps
+/// them into TTBR1 in turn, so the lock is reentrant.
+static TRANSLATION_TABLES_LOCK: ReentrantIrqSpinLock = ReentrantIrqSpinLock::new();
+
 /// Allocate a memory block of `size` starting at `virtual_address`.
 pub fn allocate_memory(
     virtual_address: usize,
@@ -131,6 +156,7 @@ pub fn allocate_memory(
     phys: PhysSource,
     flags: u64,
 ) -> Result<(), NovaError> {
+    let _tables = TRANSLATION_TABLES_LOCK.lock();
     if !virtual_address.is_multiple_of(GRANULARITY) {
         return Err(NovaError::Misalignment);
     }
@@ -223,6 +249,7 @@ pub fn alloc_page(
 
 /// Allocate a singe page in one block.
 pub fn find_free_kerne_page_in_block(start: VirtAddr) -> Result<VirtAddr, NovaError> {
+    let _tables = TRANSLATION_TABLES_LOCK.lock();
     if !start.is_multiple_of(LEVEL2_BLOCK_SIZE) {
         return Err(NovaError::Misalignment);
     }
@@ -265,6 +292,7 @@ pub fn map_page(
     base_table_ptr: *mut PageTable,
     additional_flags: u64,
 ) -> Result<(), NovaError> {
+    let _tables = TRANSLATION_TABLES_LOCK.lock();
     let (l1_off, l2_off, l3_off) = virtual_address_to_table_offset(virtual_address);
 
     let offsets = [l1_off, l2_off];
@@ -277,6 +305,96 @@ pub fn map_page(
     }
 
     table.0[l3_off] = TableEntry::page_descriptor(physical_address, additional_flags);
+    // Make the entry visible to the table walker, before the page is accessed.
+    unsafe { asm!("dsb ishst", "isb") };
+
+    Ok(())
+}
+
+/// Removes the page mapped at `virtual_address` and returns its physical address.
+///
+/// The physical page itself is not released.
+pub fn unmap_page(
+    virtual_address: VirtAddr,
+    base_table_ptr: *mut PageTable,
+) -> Result<PhysAddr, NovaError> {
+    let _tables = TRANSLATION_TABLES_LOCK.lock();
+    let (l1_off, l2_off, l3_off) = virtual_address_to_table_offset(virtual_address);
+
+    let offsets = [l1_off, l2_off];
+
+    let table = unsafe { &mut *navigate_table(base_table_ptr, &offsets, false)? };
+    let entry = table.0[l3_off];
+
+    if entry.is_invalid() {
+        return Err(NovaError::Paging("Page not mapped."));
+    }
+
+    table.0[l3_off] = TableEntry::invalid();
+    invalidate_tlb_page(virtual_address);
+
+    Ok(entry.address())
+}
+
+/// Returns the physical address `virtual_address` is mapped to by a page.
+pub fn translate(
+    virtual_address: VirtAddr,
+    base_table_ptr: *mut PageTable,
+) -> Result<PhysAddr, NovaError> {
+    let _tables = TRANSLATION_TABLES_LOCK.lock();
+    let (l1_off, l2_off, l3_off) = virtual_address_to_table_offset(virtual_address);
+
+    let offsets = [l1_off, l2_off];
+
+    let table = unsafe { &*navigate_table(base_table_ptr, &offsets, false)? };
+    let entry = table.0[l3_off];
+
+    if entry.is_invalid() {
+        return Err(NovaError::Paging("Page not mapped."));
+    }
+
+    Ok(entry.address() | (virtual_address & (GRANULARITY - 1)))
+}
+
+/// Returns the page descriptor mapping `virtual_address`.
+pub fn page_entry(
+    virtual_address: VirtAddr,
+    base_table_ptr: *mut PageTable,
+) -> Result<TableEntry, NovaError> {
+    let _tables = TRANSLATION_TABLES_LOCK.lock();
+    let (l1_off, l2_off, l3_off) = virtual_address_to_table_offset(virtual_address);
+
+    let offsets = [l1_off, l2_off];
+
+    let table = unsafe { &*navigate_table(base_table_ptr, &offsets, false)? };
+    let entry = table.0[l3_off];
+
+    if entry.is_invalid() {
+        return Err(NovaError::Paging("Page not mapped."));
+    }
+    Ok(entry)
+}
+
+/// Replaces the page mapped at `virtual_address`, the previous physical page
+/// isn't released.
+pub fn remap_page(
+    virtual_address: VirtAddr,
+    physical_address: PhysAddr,
+    base_table_ptr: *mut PageTable,
+    additional_flags: u64,
+) -> Result<(), NovaError> {
+    let _tables = TRANSLATION_TABLES_LOCK.lock();
+    let (l1_off, l2_off, l3_off) = virtual_address_to_table_offset(virtual_address);
+
+    let offsets = [l1_off, l2_off];
+
+    let table = unsafe { &mut *navigate_table(base_table_ptr, &offsets, false)? };
+    if table.0[l3_off].is_invalid() {
+        return Err(NovaError::Paging("Page not mapped."));
+    }
+
+    table.0[l3_off] = TableEntry::page_descriptor(physical_address, additional_flags);
+    invalidate_tlb_page(virtual_address);
 
     Ok(())
 }
@@ -307,6 +425,7 @@ pub fn map_l2_block(
     base_table_ptr: *mut PageTable,
     additional_flags: u64,
 ) -> Result<(), NovaError> {
+    let _tables = TRANSLATION_TABLES_LOCK.lock();
     let (l1_off, l2_off, _) = virtual_address_to_table_offset(virtual_addr);
     let offsets = [l1_off];
     let table_ptr = navigate_table(base_table_ptr, &offsets, true)?;
@@ -394,15 +513,9 @@ fn next_table(
             if !create_missing {
                 return Err(NovaError::Paging("No table defined."));
             }
-            let new_phys_page_table_address = reserve_page();
+            let new_phys_page_table_address = allocate_frame()?;
 
             table.0[offset] = TableEntry::table_descriptor(new_phys_page_table_address);
-            map_page(
-                phys_table_to_kernel_space(new_phys_page_table_address),
-                new_phys_page_table_address,
-                &raw mut TRANSLATIONTABLE_TTBR1,
-                NORMAL_MEM | WRITABLE | PXN | UXN,
-            )?;
 
             Ok(resolve_table_addr(table.0[offset].address()) as *mut PageTable)
         }
@@ -414,6 +527,180 @@ fn next_table(
     }
 }
 
+/// Reserves a zeroed page, which is accessible through [`frame_address`] until
+/// it is released by [`release_frame`].
+pub fn allocate_frame() -> Result<PhysAddr, NovaError> {
+    let _tables = TRANSLATION_TABLES_LOCK.lock();
+    let physical_address = try_reserve_page()?;
+
+    if let Err(err) = map_page(
+        phys_table_to_kernel_space(physical_address),
+        physical_address,
+        &raw mut TRANSLATIONTABLE_TTBR1,
+        NORMAL_MEM | WRITABLE | PXN | UXN,
+    ) {
+        free_page(physical_address);
+        return Err(err);
+    }
+    unsafe {
+        core::ptr::write_bytes(
+            resolve_table_addr(physical_address) as *mut u8,
+            0,
+            GRANULARITY,
+        );
+    }
+    set_frame_references(physical_address, 1);
+
+    Ok(physical_address)
+}
+
+/// Adds a reference to a frame reserved by [`allocate_frame`], which is then
+/// only freed once every reference has been released.
+///
+/// Fails if the frame already has the maximum number of references.
+pub fn share_frame(physical_address: PhysAddr) -> Result<(), NovaError> {
+    acquire_frame_reference(physical_address)
+}
+
+/// Releases a reference to a frame reserved by [`allocate_frame`], the frame
+/// is freed with its last reference.
+pub fn release_frame(physical_address: PhysAddr) {
+    let _tables = TRANSLATION_TABLES_LOCK.lock();
+    let page = physical_address & !(GRANULARITY - 1);
+    if release_frame_reference(page) > 0 {
+        return;
+    }
+
+    if unmap_page(
+        phys_table_to_kernel_space(page),
+        &raw mut TRANSLATIONTABLE_TTBR1,
+    )
+    .is_ok()
+    {
+        free_page(page);
+    }
+}
+
+/// Kernel virtual address of a frame reserved by [`allocate_frame`].
+pub fn frame_address(physical_address: PhysAddr) -> VirtAddr {
+    resolve_table_addr(physical_address)
+}
+
+/// Releases all tables and frames of the address space rooted at
+/// `root_physical_address`, which aren't shared with the kernel's TTBR0 table.
+///
+/// The root table itself is released as well.
+pub fn release_address_space(root_physical_address: PhysAddr) {
+    let _tables = TRANSLATION_TABLES_LOCK.lock();
+    let root = unsafe { &*(resolve_table_addr(root_physical_address) as *const PageTable) };
+    let shared = unsafe { &*core::ptr::addr_of!(TRANSLATIONTABLE_TTBR0) };
+
+    for (entry, shared_entry) in root.0.iter().zip(shared.0.iter()) {
+        if !entry.is_invalid() && entry.value != shared_entry.value {
+            release_table(entry.address(), 2);
+        }
+    }
+
+    release_frame(root_physical_address);
+}
+
+/// Calls `f` with the virtual address and descriptor of every page of the
+/// address space rooted at `root_physical_address`, which isn't shared with the
+/// kernel's TTBR0 table.
+///
+/// Changes to the descriptors need a TLB invalidation to take effect.
+pub fn for_each_private_page(
+    root_physical_address: PhysAddr,
+    mut f: impl FnMut(VirtAddr, &mut TableEntry),
+) {
+    let _tables = TRANSLATION_TABLES_LOCK.lock();
+    let root = unsafe { &*(resolve_table_addr(root_physical_address) as *const PageTable) };
+    let shared = unsafe { &*core::ptr::addr_of!(TRANSLATIONTABLE_TTBR0) };
+
+    for (l1_off, (entry, shared_entry)) in root.0.iter().zip(shared.0.iter()).enumerate() {
+        if entry.is_invalid() || entry.value == shared_entry.value {
+            continue;
+        }
+
+        let level2 = unsafe { &*(resolve_table_addr(entry.address()) as *const PageTable) };
+        for (l2_off, entry) in level2.0.iter().enumerate() {
+            if entry.value & 0b11 != TABLE {
+                continue;
+            }
+
+            let level3 = unsafe { &mut *(resolve_table_addr(entry.address()) as *mut PageTable) };
+            for (l3_off, entry) in level3.0.iter_mut().enumerate() {
+                if !entry.is_invalid() {
+                    let virtual_address =
+                        ((l1_off * TABLE_ENTRY_COUNT + l2_off) * TABLE_ENTRY_COUNT + l3_off)
+                            * GRANULARITY;
+                    f(virtual_address, entry);
+                }
+            }
+        }
+    }
+}
+
+/// Releases a table of `level` together with everything it maps.
+fn release_table(table_physical_address: PhysAddr, level: usize) {
+    let table = unsafe { &*(resolve_table_addr(table_physical_address) as *const PageTable) };
+
+    for entry in table.0.iter().filter(|entry| !entry.is_invalid()) {
+        if level == 3 {
+            release_frame(entry.address());
+        } else if entry.value & 0b11 == TABLE {
+            release_table(entry.address(), level + 1);
+        } else if level == 2 {
+            free_block(entry.address());
+        }
+    }
+
+    release_frame(table_physical_address);
+}
+
+/// Invalidates the TLB entries of `virtual_address` for all ASIDs.
+///
+/// The inner shareable TLBI reaches every core and drops cached walks of all
+/// levels, and the final `dsb ish` waits for all of them to complete. So
+/// unmapped frames and tables may be reused right away, without an IPI.
+pub fn invalidate_tlb_page(virtual_address: VirtAddr) {
+    let page = (virtual_address >> 12) & 0xFFF_FFFF_FFFF;
+    unsafe {
+        asm!(
+            "dsb ishst",
+            "tlbi vaae1is, {}",
+            "dsb ish",
+            "isb",
+            in(reg) page
+        )
+    };
+}
+
+/// Invalidates all non-global TLB entries tagged with `asid`.
+pub fn invalidate_tlb_asid(asid: u16) {
+    unsafe {
+        asm!(
+            "dsb ishst",
+            "tlbi aside1is, {}",
+            "dsb ish",
+            "isb",
+            in(reg) (asid as u64) << 48
+        )
+    };
+}
+
+/// Switches TTBR0 to the given translation table and ASID.
+pub fn set_ttbr0(table_physical_address: PhysAddr, asid: u16) {
+    let value = (table_physical_address as u64 & 0x0000_FFFF_FFFF_FFFE) | ((asid as u64) << 48);
+    unsafe { asm!("msr TTBR0_EL1, {}", "isb", in(reg) value) };
+}
+
+/// Switches TTBR0 back to the kernel translation table.
+pub fn set_kernel_ttbr0() {
+    let table = core::ptr::addr_of!(TRANSLATIONTABLE_TTBR0) as usize;
+    set_ttbr0(table & !KERNEL_VIRTUAL_MEM_SPACE, 0);
+}
+
 /// Converts a physical table address and returns the corresponding virtual address depending on EL.
 ///
 /// - `== EL0` -> panic
@@ -1,95 +1,137 @@
-use crate::aarch64::mmu::{PhysAddr, GRANULARITY, L2_BLOCK_BITMAP_WORDS, MAX_PAGE_COUNT};
+use crate::{
+    aarch64::mmu::{PhysAddr, GRANULARITY, L2_BLOCK_BITMAP_WORDS, MAX_PAGE_COUNT},
+    sync::IrqSpinLock,
+};
 use nova_error::NovaError;
 
 struct PagingMap {
     bitmap: [u64; MAX_PAGE_COUNT / 64],
+    /// Number of references to each page, used for pages shared between address spaces.
+    references: [u16; MAX_PAGE_COUNT],
 }
 
-static mut PAGING_BITMAP: PagingMap = PagingMap {
+static PAGING_MAP: IrqSpinLock<PagingMap> = IrqSpinLock::new(PagingMap {
     bitmap: [0; MAX_PAGE_COUNT / 64],
-};
+    references: [0; MAX_PAGE_COUNT],
+});
 
 pub fn reserve_page() -> PhysAddr {
-    if let Some(address) = find_unallocated_page() {
-        let page = address / GRANULARITY;
-        let word_index = page / 64;
-        unsafe { PAGING_BITMAP.bitmap[word_index] |= 1 << (page % 64) };
-        return address;
-    }
-    panic!("Out of Memory!");
+    try_reserve_page().expect("Out of Memory!")
+}
+
+/// Reserves a page, failing instead of panicking if none is left.
+pub fn try_reserve_page() -> Result<PhysAddr, NovaError> {
+    let mut map = PAGING_MAP.lock();
+    let address = map.find_unallocated_page().ok_or(NovaError::OutOfMeomory)?;
+    let page = address / GRANULARITY;
+    map.bitmap[page / 64] |= 1 << (page % 64);
+    Ok(address)
 }
 
 pub fn reserve_page_explicit(physical_address: usize) -> Result<PhysAddr, NovaError> {
     let page = physical_address / GRANULARITY;
     let word_index = page / 64;
+    let mut map = PAGING_MAP.lock();
 
-    if unsafe { PAGING_BITMAP.bitmap[word_index] } & (1 << (page % 64)) > 0 {
+    if map.bitmap[word_index] & (1 << (page % 64)) > 0 {
         return Err(NovaError::Paging("Page PA already taken."));
     }
 
-    unsafe { PAGING_BITMAP.bitmap[word_index] |= 1 << (page % 64) };
+    map.bitmap[word_index] |= 1 << (page % 64);
     Ok(physical_address)
 }
 
+/// Releases a page reserved by `reserve_page` or `reserve_page_explicit`.
+pub fn free_page(physical_address: PhysAddr) {
+    let page = physical_address / GRANULARITY;
+    PAGING_MAP.lock().bitmap[page / 64] &= !(1 << (page % 64));
+}
+
+pub fn set_frame_references(physical_address: PhysAddr, references: u16) {
+    PAGING_MAP.lock().references[physical_address / GRANULARITY] = references;
+}
+
+pub fn frame_references(physical_address: PhysAddr) -> u16 {
+    PAGING_MAP.lock().references[physical_address / GRANULARITY]
+}
+
+/// Adds a reference, failing once the counter is exhausted.
+pub fn acquire_frame_reference(physical_address: PhysAddr) -> Result<(), NovaError> {
+    let mut map = PAGING_MAP.lock();
+    let references = &mut map.references[physical_address / GRANULARITY];
+    *references = references.checked_add(1).ok_or(NovaError::OutOfMeomory)?;
+    Ok(())
+}
+
+/// Drops a reference and returns the number of remaining ones.
+pub fn release_frame_reference(physical_address: PhysAddr) -> u16 {
+    let page = physical_address / GRANULARITY;
+    let mut map = PAGING_MAP.lock();
+    map.references[page] = map.references[page].saturating_sub(1);
+    map.references[page]
+}
+
 pub fn reserve_block() -> usize {
-    if let Some(start) = find_contiguous_free_bitmap_words(L2_BLOCK_BITMAP_WORDS) {
-        for j in 0..L2_BLOCK_BITMAP_WORDS {
-            unsafe { PAGING_BITMAP.bitmap[start + j] = u64::MAX };
-        }
+    let mut map = PAGING_MAP.lock();
+    if let Some(start) = map.find_contiguous_free_bitmap_words(L2_BLOCK_BITMAP_WORDS) {
+        map.bitmap[start..start + L2_BLOCK_BITMAP_WORDS].fill(u64::MAX);
         return start * 64 * GRANULARITY;
     }
 
     panic!("Out of Memory!");
 }
 
+/// Releases a block reserved by `reserve_block` or `reserve_block_explicit`.
+pub fn free_block(physical_address: PhysAddr) {
+    let word_index = physical_address / GRANULARITY / 64;
+    PAGING_MAP.lock().bitmap[word_index..word_index + L2_BLOCK_BITMAP_WORDS].fill(0);
+}
+
 pub fn reserve_block_explicit(physical_address: usize) -> Result<(), NovaError> {
-    let page = physical_address / GRANULARITY;
-    for i in 0..L2_BLOCK_BITMAP_WORDS {
-        unsafe {
-            if PAGING_BITMAP.bitmap[(page / 64) + i] != 0 {
-                return Err(NovaError::Paging("Block PA already taken."));
-            }
-        };
-    }
-    for i in 0..L2_BLOCK_BITMAP_WORDS {
-        unsafe {
-            PAGING_BITMAP.bitmap[(page / 64) + i] = u64::MAX;
-        };
+    let word_index = physical_address / GRANULARITY / 64;
+    let mut map = PAGING_MAP.lock();
+    let words = &mut map.bitmap[word_index..word_index + L2_BLOCK_BITMAP_WORDS];
+
+    if words.iter().any(|word| *word != 0) {
+        return Err(NovaError::Paging("Block PA already taken."));
     }
+    words.fill(u64::MAX);
     Ok(())
 }
 
-fn find_unallocated_page() -> Option<usize> {
-    for (i, entry) in unsafe { PAGING_BITMAP.bitmap }.iter().enumerate() {
-        if *entry != u64::MAX {
-            for offset in 0..64 {
-                if entry >> offset & 0b1 == 0 {
-                    return Some((i * 64 + offset) * GRANULARITY);
+impl PagingMap {
+    fn find_unallocated_page(&self) -> Option<usize> {
+        for (i, entry) in self.bitmap.iter().enumerate() {
+            if *entry != u64::MAX {
+                for offset in 0..64 {
+                    if entry >> offset & 0b1 == 0 {
+                        return Some((i * 64 + offset) * GRANULARITY);
+                    }
                 }
             }
         }
+        None
     }
-    None
-}
 
-fn find_contiguous_free_bitmap_words(required_words: usize) -> Option<usize> {
-    let mut run_start = 0;
-    let mut run_len = 0;
+    fn find_contiguous_free_bitmap_words(&self, required_words: usize) -> Option<usize> {
+        let mut run_start = 0;
+        let mut run_len = 0;
 
-    for (i, entry) in unsafe { PAGING_BITMAP.bitmap }.iter().enumerate() {
-        if *entry == 0 {
-            if run_len == 0 {
-                run_start = i;
-            }
-            run_len += 1;
+        for (i, entry) in self.bitmap.iter().enumerate() {
+            if *entry == 0 {
+                if run_len == 0 {
+                    run_start = i;
+                }
+                run_len += 1;
 
-            if run_len == required_words {
-                return Some(run_start);
+                if run_len == required_words {
+                    return Some(run_start);
+                }
+            } else {
+                run_len = 0;
             }
-        } else {
-            run_len = 0;
         }
-    }
 
-    None
+        None
+    }
 }
@@ -1,2 +1,4 @@
+pub mod backtrace;
+pub mod generic_timer;
 pub mod mmu;
 pub mod registers;
@@ -22,6 +22,27 @@ pub mod daif {
     pub fn unmask_irq() {
         unsafe { asm!("msr DAIFClr, #0x2", options(nomem, nostack)) }
     }
+
+    /// Masks IRQs and FIQs and returns the previous DAIF, see [`restore`].
+    #[inline(always)]
+    pub fn save_and_mask() -> u64 {
+        let daif: u64;
+        unsafe {
+            asm!(
+                "mrs {}, DAIF",
+                "msr DAIFSet, #0x3",
+                out(reg) daif,
+                options(nostack)
+            )
+        };
+        daif
+    }
+
+    /// Restores DAIF saved by [`save_and_mask`].
+    #[inline(always)]
+    pub fn restore(daif: u64) {
+        unsafe { asm!("msr DAIF, {}", in(reg) daif, options(nostack)) }
+    }
 }
 
 #[macro_export]
@@ -46,14 +67,29 @@ psr!(TCR_EL1, u64);
 
 psr!(ID_AA64MMFR0_EL1, u64);
 
+psr!(ID_AA64MMFR1_EL1, u64);
+
 psr!(ESR_EL1, u32);
 
 psr!(SPSR_EL1, u32);
 
 psr!(ELR_EL1, u64);
 
+psr!(FAR_EL1, u64);
+
 psr!(SCTLR_EL1, u64);
 
+psr!(PAR_EL1, u64);
+
+psr!(MPIDR_EL1, u64);
+
+psr!(CNTFRQ_EL0, u64);
+
+/// Index of the core executing the caller, `Aff0` of MPIDR_EL1.
+pub fn current_core() -> usize {
+    (read_mpidr_el1() & 0xFF) as usize
+}
+
 pub fn read_exception_source_el() -> u32 {
     read_spsr_el1() & 0b1111
 }
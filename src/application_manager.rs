@@ -1,145 +1,1392 @@
 use crate::{
     aarch64::mmu::{
-        find_free_kerne_page_in_block, map_page, physical_mapping::reserve_page, PageTable,
-        TableEntry, VirtAddr, NORMAL_MEM, TRANSLATIONTABLE_TTBR0, TRANSLATIONTABLE_TTBR1, WRITABLE,
+        invalidate_tlb_asid, set_kernel_ttbr0, set_ttbr0, VirtAddr, EL0_ACCESSIBLE, GRANULARITY,
+        NORMAL_MEM, PXN, UXN, WRITABLE,
     },
-    configuration::memory_mapping::{APPLICATION_TRANSLATION_TABLE_VA, EL0_STACK_TOP},
+    aarch64::{generic_timer::set_tick_handler, registers::current_core},
+    configuration::memory_mapping::{EL0_STACK_BOTTOM, EL0_STACK_SIZE, EL0_STACK_TOP},
+    interrupt_handlers::{set_return_to_kernel_loop, TrapFrame},
+    peripherals::{rng::fill_random, uart::write_console},
+    pi3::timer::current_time_us,
+    smp::{
+        ipi::{send_ipi, Ipi},
+        ready_cores_in, CpuMask, ALL_CORES, CORE_COUNT,
+    },
+    sync::{IrqSpinLock, PerCpu},
+    syscalls::Errno,
+    timer::{self, TimerId},
+};
+use alloc::{
+    collections::{btree_map::BTreeMap, btree_set::BTreeSet, vec_deque::VecDeque},
+    string::String,
+    vec,
+    vec::Vec,
+};
+use core::{
+    arch::asm,
+    sync::atomic::{AtomicU64, Ordering},
 };
-use alloc::vec::Vec;
-use core::{arch::asm, mem, ptr::write_volatile};
-use log::error;
+use initial_stack::{required_size, Layout, AT_ENTRY, AT_PAGESZ, AT_PHDR, AT_PHNUM, RANDOM_BYTES};
+use log::{error, info};
 use nova_error::NovaError;
-use spin::Mutex;
+
+pub mod address_space;
+pub mod files;
+pub mod ipc;
+pub mod process;
+pub mod scheduler;
+pub mod shared_memory;
+pub mod signal;
+
+use address_space::{AddressSpace, FaultAccess, VmaKind};
+use files::{Fd, File, FileTable, Pipes};
+use ipc::{ChannelId, Channels, Message, MESSAGE_SIZE};
+use process::{ExitStatus, Pid, Process, ProcessInfo, ProcessState, Signal, WaitReason};
+use scheduler::RunQueues;
+use shared_memory::{SharedMemoryId, SharedMemoryObjects};
+use signal::{SignalFrame, SignalState, SIG_DFL};
+
+/// Condition flags of SPSR_EL1.
+const SPSR_NZCV: u64 = 0xF000_0000;
+
+/// Environment every application is started with.
+const DEFAULT_ENVIRONMENT: &[&str] = &["TERM=vt100"];
+
+/// Ticks of the generic timer a process runs before the next ready one gets
+/// the core.
+const TIME_SLICE_TICKS: u64 = 2;
+
+/// Ticks the process running on each core has used of its time slice.
+static SLICE_TICKS: PerCpu<AtomicU64> = PerCpu::new([const { AtomicU64::new(0) }; CORE_COUNT]);
 
 struct AppManager {
-    apps: Option<Vec<Application>>,
+    /// Entry points of the registered applications, indexed by app id.
+    apps: Option<Vec<VirtAddr>>,
+    processes: BTreeMap<Pid, Process>,
+    run_queues: RunQueues,
+    /// Process running on each core.
+    running: [Option<Pid>; CORE_COUNT],
+    next_pid: Pid,
+    channels: Channels,
+    shared_memory: SharedMemoryObjects,
+    pipes: Pipes,
+    /// Console input, which hasn't been read by a process yet.
+    console_input: VecDeque<u8>,
+    /// Sleeping processes, ordered by their deadline.
+    sleepers: BTreeSet<(u64, Pid)>,
+    /// Timer waking the earliest sleeper, with its deadline.
+    sleep_timer: Option<(u64, TimerId)>,
 }
 
 impl AppManager {
     const fn new() -> Self {
-        Self { apps: None }
+        Self {
+            apps: None,
+            processes: BTreeMap::new(),
+            run_queues: RunQueues::new(),
+            running: [None; CORE_COUNT],
+            next_pid: 1,
+            channels: Channels::new(),
+            shared_memory: SharedMemoryObjects::new(),
+            pipes: Pipes::new(),
+            console_input: VecDeque::new(),
+            sleepers: BTreeSet::new(),
+            sleep_timer: None,
+        }
+    }
+
+    /// Starts a process of the app `app_id` with the file descriptors `files`.
+    ///
+    /// The references to the files are handed over to the process, or released
+    /// if it can't be started.
+    fn spawn(
+        &mut self,
+        app_id: usize,
+        args: Vec<&str>,
+        files: FileTable,
+    ) -> Result<Pid, NovaError> {
+        let app = self.app_entry(app_id).and_then(|start_addr| {
+            let mut app = Application::new(start_addr)?;
+            let context = app.initial_context(&args, DEFAULT_ENVIRONMENT)?;
+            Ok((app, context))
+        });
+        let (app, context) = match app {
+            Ok(app) => app,
+            Err(err) => {
+                self.release_files(&files);
+                return Err(err);
+            }
+        };
+
+        let pid = self.next_pid;
+        self.next_pid += 1;
+
+        self.processes.insert(
+            pid,
+            Process {
+                pid,
+                parent: None,
+                app_id,
+                state: ProcessState::Ready,
+                app: Some(app),
+                files,
+                signals: SignalState::default(),
+                context,
+                affinity: ALL_CORES,
+                core: None,
+            },
+        );
+        self.enqueue(pid);
+        Ok(pid)
+    }
+
+    /// Entry point of the app `app_id`.
+    fn app_entry(&self, app_id: usize) -> Result<VirtAddr, NovaError> {
+        self.apps
+            .as_ref()
+            .ok_or(NovaError::General("AppManager not initalized."))?
+            .get(app_id)
+            .copied()
+            .ok_or(NovaError::General("Invalid app id."))
+    }
+
+    /// Starts one process per command, the stdout of each is connected to the
+    /// stdin of the next one by a pipe.
+    fn spawn_pipeline(&mut self, commands: Vec<(usize, Vec<&str>)>) -> Result<Vec<Pid>, NovaError> {
+        let mut pids = Vec::new();
+        let mut stdin = File::Console;
+        let count = commands.len();
+
+        for (index, (app_id, args)) in commands.into_iter().enumerate() {
+            let (next_stdin, stdout) = if index + 1 < count {
+                self.pipes.create()
+            } else {
+                (File::Console, File::Console)
+            };
+
+            match self.spawn(app_id, args, FileTable::with_stdio(stdin, stdout)) {
+                Ok(pid) => pids.push(pid),
+                Err(err) => {
+                    self.release_file(next_stdin);
+                    return Err(err);
+                }
+            }
+            stdin = next_stdin;
+        }
+        Ok(pids)
+    }
+
+    /// Duplicates the current process, see [`AddressSpace::fork`].
+    ///
+    /// The child continues from the context in `frame`, but sees `0` as result
+    /// of the syscall.
+    fn fork(&mut self, frame: &TrapFrame) -> Result<Pid, NovaError> {
+        let parent_pid = self
+            .current()
+            .ok_or(NovaError::General("No process running."))?;
+        let parent = self
+            .processes
+            .get_mut(&parent_pid)
+            .ok_or(NovaError::General("No process running."))?;
+        let app_id = parent.app_id;
+        let files = parent.files.clone();
+        let signals = parent.signals.forked();
+        let affinity = parent.affinity;
+        let asid = parent.asid();
+        let app = parent
+            .app
+            .as_mut()
+            .ok_or(NovaError::General("Process already terminated."))?
+            .fork();
+        // Pages of the parent might have become read-only, even if forking failed.
+        invalidate_tlb_asid(asid);
+        let app = app?;
+
+        let pid = self.next_pid;
+        self.next_pid += 1;
+
+        self.processes.insert(
+            pid,
+            Process {
+                pid,
+                parent: Some(parent_pid),
+                app_id,
+                state: ProcessState::Ready,
+                app: Some(app),
+                files: files.clone(),
+                signals,
+                context: TrapFrame { x0: 0, ..*frame },
+                affinity,
+                core: None,
+            },
+        );
+        for file in files.iter() {
+            self.pipes.acquire(file);
+        }
+        self.enqueue(pid);
+        Ok(pid)
+    }
+
+    /// Replaces the program of the current process by the app `app_id`.
+    ///
+    /// On success `frame` holds the initial context of the new program.
+    fn exec(
+        &mut self,
+        frame: &mut TrapFrame,
+        app_id: usize,
+        args: &[&str],
+    ) -> Result<(), NovaError> {
+        let start_addr = self.app_entry(app_id)?;
+
+        let process = self
+            .current()
+            .and_then(|pid| self.processes.get_mut(&pid))
+            .ok_or(NovaError::General("No process running."))?;
+
+        let mut app = Application::new(start_addr)?;
+        let context = app.initial_context(args, DEFAULT_ENVIRONMENT)?;
+
+        let asid = process.asid();
+        app.activate(asid);
+        drop(process.app.replace(app));
+        invalidate_tlb_asid(asid);
+
+        process.app_id = app_id;
+        process.signals.reset_handlers();
+        *frame = context;
+        Ok(())
+    }
+
+    /// Terminates a process and hands its exit status to waiting processes.
+    ///
+    /// The process stays a zombie, while its parent may still collect the status.
+    fn terminate(&mut self, pid: Pid, status: ExitStatus) {
+        let Some(process) = self.processes.get_mut(&pid) else {
+            return;
+        };
+
+        // The address space of a process running on another core can't be
+        // released under its feet, that core kills it on the IPI instead.
+        if process.state == ProcessState::Running {
+            if let Some(core) = process.core.filter(|core| *core != current_core()) {
+                process.signals.raise(Signal::SIGKILL);
+                send_ipi(core, Ipi::Reschedule);
+                return;
+            }
+        }
+        if self.running[current_core()] == Some(pid) {
+            set_kernel_ttbr0();
+        }
+
+        let asid = process.asid();
+        process.state = ProcessState::Zombie(status);
+        drop(process.app.take());
+        invalidate_tlb_asid(asid);
+
+        let parent = process.parent;
+        info!("Process {} {}", pid, status);
+
+        let files = core::mem::replace(&mut process.files, FileTable::empty());
+        self.release_files(&files);
+        for channel in self.channels.close_owned_by(pid) {
+            self.restart_blocked(WaitReason::Channel(channel));
+        }
+        self.shared_memory.close_owned_by(pid);
+
+        let orphaned_zombies: Vec<Pid> = self
+            .processes
+            .values_mut()
+            .filter(|child| child.parent == Some(pid))
+            .filter_map(|child| {
+                child.parent = None;
+                matches!(child.state, ProcessState::Zombie(_)).then_some(child.pid)
+            })
+            .collect();
+        for child in orphaned_zombies {
+            self.processes.remove(&child);
+        }
+
+        let waiters: Vec<Pid> = self
+            .processes
+            .values_mut()
+            .filter(|waiter| waiter.state == ProcessState::Blocked(WaitReason::Process(pid)))
+            .map(|waiter| {
+                waiter.context.x0 = status.wait_status() as u64;
+                waiter.state = ProcessState::Ready;
+                waiter.pid
+            })
+            .collect();
+        let collected = !waiters.is_empty();
+        for waiter in waiters {
+            self.enqueue(waiter);
+        }
+
+        if collected || parent.is_none() {
+            self.processes.remove(&pid);
+        }
+    }
+
+    /// Collects the exit status of `pid`, which has to be a child of the
+    /// current process.
+    ///
+    /// Returns `None` if the current process has been blocked until `pid` terminates.
+    fn wait(&mut self, frame: &TrapFrame, pid: Pid) -> Result<Option<ExitStatus>, Errno> {
+        let current = self.current().ok_or(Errno::ECHILD)?;
+
+        match self
+            .processes
+            .get(&pid)
+            .filter(|target| target.parent == Some(current))
+            .map(|target| target.state)
+        {
+            None => Err(Errno::ECHILD),
+            Some(ProcessState::Zombie(status)) => {
+                self.processes.remove(&pid);
+                Ok(Some(status))
+            }
+            Some(_) => {
+                self.block(frame, WaitReason::Process(pid));
+                Ok(None)
+            }
+        }
+    }
+
+    fn create_channel(&mut self) -> Result<ChannelId, Errno> {
+        let pid = self.current().ok_or(Errno::EINVAL)?;
+        Ok(self.channels.create(pid))
+    }
+
+    fn close_channel(&mut self, id: ChannelId) -> Result<(), Errno> {
+        let pid = self.current().ok_or(Errno::EINVAL)?;
+        self.channels.close(id, pid)?;
+        self.restart_blocked(WaitReason::Channel(id));
+        Ok(())
+    }
+
+    /// Queues `data` on the channel `id`.
+    ///
+    /// The mmap page at `page` is moved from the current process to the message.
+    fn send(&mut self, id: ChannelId, data: &[u8], page: Option<VirtAddr>) -> Result<(), Errno> {
+        if data.len() > MESSAGE_SIZE {
+            return Err(Errno::EMSGSIZE);
+        }
+        self.channels.reserve(id)?;
+
+        let frame = match page {
+            Some(page) => Some(
+                self.current_app()
+                    .ok_or(Errno::EINVAL)?
+                    .address_space_mut()
+                    .take_page(page)
+                    .map_err(|err| match err {
+                        NovaError::OutOfMeomory => Errno::ENOMEM,
+                        _ => Errno::EINVAL,
+                    })?,
+            ),
+            None => None,
+        };
+
+        let mut message = Message {
+            data: [0; MESSAGE_SIZE],
+            len: data.len(),
+            frame,
+        };
+        message.data[..data.len()].copy_from_slice(data);
+
+        self.channels.push(id, message)?;
+        self.restart_blocked(WaitReason::Channel(id));
+        Ok(())
+    }
+
+    /// Takes the oldest message of the channel `id`.
+    ///
+    /// A transferred frame is mapped into the current process with `page_flags`
+    /// and its address is returned with the message. Returns `None` if the
+    /// current process has been blocked until a message arrives.
+    fn receive(
+        &mut self,
+        frame: &TrapFrame,
+        id: ChannelId,
+        nonblocking: bool,
+        page_flags: u64,
+    ) -> Result<Option<(Message, Option<VirtAddr>)>, Errno> {
+        let Some(mut message) = self.channels.pop(id)? else {
+            if nonblocking {
+                return Err(Errno::EAGAIN);
+            }
+            self.block(frame, WaitReason::Channel(id));
+            return Ok(None);
+        };
+
+        let page = match message.frame {
+            Some(physical_address) => {
+                let page = self
+                    .current_app()
+                    .ok_or(Errno::EINVAL)?
+                    .address_space_mut()
+                    .map_frame(physical_address, page_flags)
+                    .map_err(|_| Errno::ENOMEM)?;
+                message.frame = None;
+                Some(page)
+            }
+            None => None,
+        };
+        Ok(Some((message, page)))
+    }
+
+    /// Makes the processes blocked for `reason` ready, their syscall is
+    /// restarted once they run again.
+    fn restart_blocked(&mut self, reason: WaitReason) {
+        let restarted: Vec<Pid> = self
+            .processes
+            .values_mut()
+            .filter(|process| process.state == ProcessState::Blocked(reason))
+            .map(|process| {
+                // Step back to the `svc` instruction.
+                process.context.elr -= 4;
+                process.state = ProcessState::Ready;
+                process.pid
+            })
+            .collect();
+        for pid in restarted {
+            self.enqueue(pid);
+        }
+    }
+
+    fn create_shared_memory(
+        &mut self,
+        name: Option<String>,
+        size_bytes: usize,
+    ) -> Result<SharedMemoryId, Errno> {
+        let pid = self.current().ok_or(Errno::EINVAL)?;
+        self.shared_memory.create(pid, name, size_bytes)
+    }
+
+    /// Maps the shared memory object `id` into the current process and returns
+    /// its address.
+    fn map_shared_memory(&mut self, id: SharedMemoryId, flags: u64) -> Result<VirtAddr, Errno> {
+        let frames = self.shared_memory.get(id)?.frames().to_vec();
+        self.current_app()
+            .ok_or(Errno::EINVAL)?
+            .address_space_mut()
+            .map_shared(&frames, flags)
+            .map_err(|_| Errno::ENOMEM)
+    }
+
+    fn close_shared_memory(&mut self, id: SharedMemoryId) -> Result<(), Errno> {
+        let pid = self.current().ok_or(Errno::EINVAL)?;
+        self.shared_memory.close(id, pid)
+    }
+
+    /// Reads from the file `fd` of the current process into `buffer`.
+    ///
+    /// Returns `None` if the current process has been blocked until data is available.
+    fn read(
+        &mut self,
+        frame: &TrapFrame,
+        fd: Fd,
+        buffer: &mut [u8],
+    ) -> Result<Option<usize>, Errno> {
+        let (count, reason) = match self.current_files()?.get(fd)? {
+            File::Console => {
+                let count = buffer.len().min(self.console_input.len());
+                for (byte, value) in buffer.iter_mut().zip(self.console_input.drain(..count)) {
+                    *byte = value;
+                }
+                (
+                    (count > 0 || buffer.is_empty()).then_some(count),
+                    WaitReason::ConsoleInput,
+                )
+            }
+            File::PipeReader(id) => (self.pipes.read(id, buffer)?, WaitReason::Pipe(id)),
+            File::PipeWriter(_) => return Err(Errno::EBADF),
+        };
+
+        match count {
+            Some(count) => {
+                // Writers waiting for space.
+                if let WaitReason::Pipe(_) = reason {
+                    self.restart_blocked(reason);
+                }
+                Ok(Some(count))
+            }
+            None => {
+                self.block(frame, reason);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Writes `data` to the file `fd` of the current process and returns the
+    /// number of bytes written.
+    ///
+    /// Returns `None` if the current process has been blocked until the pipe
+    /// has space.
+    fn write(&mut self, frame: &TrapFrame, fd: Fd, data: &[u8]) -> Result<Option<usize>, Errno> {
+        match self.current_files()?.get(fd)? {
+            File::Console => {
+                write_console(data);
+                Ok(Some(data.len()))
+            }
+            File::PipeWriter(id) => match self.pipes.write(id, data)? {
+                Some(count) => {
+                    self.restart_blocked(WaitReason::Pipe(id));
+                    Ok(Some(count))
+                }
+                None => {
+                    self.block(frame, WaitReason::Pipe(id));
+                    Ok(None)
+                }
+            },
+            File::PipeReader(_) => Err(Errno::EBADF),
+        }
+    }
+
+    fn close(&mut self, fd: Fd) -> Result<(), Errno> {
+        let file = self.current_files_mut()?.remove(fd)?;
+        self.release_file(file);
+        Ok(())
+    }
+
+    /// Makes `new_fd` refer to the file of `old_fd`, closing its previous file.
+    fn dup2(&mut self, old_fd: Fd, new_fd: Fd) -> Result<Fd, Errno> {
+        let file = self.current_files()?.get(old_fd)?;
+        if old_fd == new_fd {
+            return Ok(new_fd);
+        }
+
+        let replaced = self.current_files_mut()?.replace(new_fd, file)?;
+        self.pipes.acquire(file);
+        if let Some(replaced) = replaced {
+            self.release_file(replaced);
+        }
+        Ok(new_fd)
+    }
+
+    /// Creates a pipe and returns the descriptors of its read and write end.
+    fn pipe(&mut self) -> Result<(Fd, Fd), Errno> {
+        self.current_files()?;
+        let (reader, writer) = self.pipes.create();
+
+        let files = self.current_files_mut()?;
+        let fds = files.insert(reader).and_then(|reader_fd| {
+            files
+                .insert(writer)
+                .inspect_err(|_| {
+                    let _ = files.remove(reader_fd);
+                })
+                .map(|writer_fd| (reader_fd, writer_fd))
+        });
+
+        if fds.is_err() {
+            self.release_file(reader);
+            self.release_file(writer);
+        }
+        fds
+    }
+
+    /// Drops a reference to `file`, processes blocked on its pipe are woken to
+    /// notice the end of file or the missing reader.
+    fn release_file(&mut self, file: File) {
+        if let Some(id) = self.pipes.release(file) {
+            self.restart_blocked(WaitReason::Pipe(id));
+        }
+    }
+
+    fn release_files(&mut self, files: &FileTable) {
+        for file in files.iter() {
+            self.release_file(file);
+        }
+    }
+
+    /// Queues console input for processes reading from the console.
+    fn push_console_input(&mut self, byte: u8) {
+        self.console_input.push_back(byte);
+        self.restart_blocked(WaitReason::ConsoleInput);
+    }
+
+    /// Whether a process is blocked reading from the console.
+    fn awaits_console_input(&self) -> bool {
+        self.processes
+            .values()
+            .any(|process| process.state == ProcessState::Blocked(WaitReason::ConsoleInput))
+    }
+
+    fn current_files(&self) -> Result<&FileTable, Errno> {
+        self.current()
+            .and_then(|pid| self.processes.get(&pid))
+            .map(|process| &process.files)
+            .ok_or(Errno::EBADF)
+    }
+
+    fn current_files_mut(&mut self) -> Result<&mut FileTable, Errno> {
+        self.current()
+            .and_then(|pid| self.processes.get_mut(&pid))
+            .map(|process| &mut process.files)
+            .ok_or(Errno::EBADF)
+    }
+
+    fn current_app(&mut self) -> Option<&mut Application> {
+        let pid = self.current()?;
+        self.processes.get_mut(&pid)?.app.as_mut()
+    }
+
+    /// Blocks the current process until `reason` is resolved.
+    ///
+    /// A signal raised while the process was running, like from another core,
+    /// interrupts the syscall right away instead, as it would stay pending.
+    fn block(&mut self, frame: &TrapFrame, reason: WaitReason) {
+        let Some(pid) = self.current() else {
+            return;
+        };
+        let Some(process) = self.processes.get_mut(&pid) else {
+            return;
+        };
+
+        process.context = *frame;
+        if process.signals.has_deliverable() {
+            interrupt_syscall(&mut process.context, reason);
+            process.state = ProcessState::Ready;
+            self.enqueue(pid);
+        } else {
+            process.state = ProcessState::Blocked(reason);
+        }
+    }
+
+    fn yield_current(&mut self, frame: &TrapFrame) {
+        if let Some(process) = self.current().and_then(|pid| self.processes.get_mut(&pid)) {
+            process.context = *frame;
+            process.state = ProcessState::Ready;
+            let pid = process.pid;
+            self.enqueue(pid);
+        }
+    }
+
+    /// Sends `signal` to the process `pid`.
+    ///
+    /// A blocked process is woken to handle the signal. Its syscall is restarted
+    /// afterwards, only `wait` fails with `EINTR`.
+    fn send_signal(&mut self, pid: Pid, signal: Signal) -> Result<(), Errno> {
+        let process = self
+            .processes
+            .get_mut(&pid)
+            .filter(|process| !matches!(process.state, ProcessState::Zombie(_)))
+            .ok_or(Errno::ESRCH)?;
+
+        if signal == Signal::SIGKILL {
+            self.terminate(pid, ExitStatus::Signaled(signal));
+            return Ok(());
+        }
+
+        process.signals.raise(signal);
+        if let ProcessState::Blocked(reason) = process.state {
+            if !process.signals.is_blocked(signal) {
+                interrupt_syscall(&mut process.context, reason);
+                process.state = ProcessState::Ready;
+                self.enqueue(pid);
+            }
+        } else if process.state == ProcessState::Running {
+            // Delivered once the process passes through the kernel.
+            if let Some(core) = process.core.filter(|core| *core != current_core()) {
+                send_ipi(core, Ipi::Reschedule);
+            }
+        }
+        Ok(())
+    }
+
+    /// Blocks the current process until the system timer reaches `deadline_us`.
+    ///
+    /// Returns `false` if the deadline has already passed.
+    fn sleep_until(&mut self, frame: &TrapFrame, deadline_us: u64) -> bool {
+        let Some(pid) = self.current() else {
+            return false;
+        };
+        if deadline_us <= current_time_us() {
+            return false;
+        }
+
+        self.block(frame, WaitReason::Sleep(deadline_us));
+        self.sleepers.insert((deadline_us, pid));
+        self.arm_sleep_timer();
+        true
+    }
+
+    /// Makes the processes, whose deadline has passed, ready.
+    fn wake_sleepers(&mut self) {
+        self.sleep_timer = None;
+        let now = current_time_us();
+        while let Some(&(deadline, pid)) = self.sleepers.first() {
+            if deadline > now {
+                break;
+            }
+            self.sleepers.pop_first();
+
+            // Processes interrupted by a signal or terminated leave stale entries.
+            if let Some(process) = self.processes.get_mut(&pid) {
+                if process.state == ProcessState::Blocked(WaitReason::Sleep(deadline)) {
+                    process.context.x0 = 0;
+                    process.state = ProcessState::Ready;
+                    self.enqueue(pid);
+                }
+            }
+        }
+        self.arm_sleep_timer();
+    }
+
+    /// Schedules the sleep timer for the earliest deadline.
+    fn arm_sleep_timer(&mut self) {
+        let Some(&(deadline, _)) = self.sleepers.first() else {
+            return;
+        };
+
+        match self.sleep_timer {
+            Some((armed, _)) if armed <= deadline => {}
+            previous => {
+                if let Some((_, id)) = previous {
+                    timer::cancel(id);
+                }
+                self.sleep_timer = Some((deadline, timer::schedule_at(deadline, wake_sleepers)));
+            }
+        }
+    }
+
+    /// Raises `signal` for the current process after a fault, if the process
+    /// handles it.
+    ///
+    /// Returns `false` if the process has to be terminated instead.
+    fn raise_fault_signal(&mut self, signal: Signal) -> bool {
+        let Some(process) = self.current().and_then(|pid| self.processes.get_mut(&pid)) else {
+            return false;
+        };
+
+        if process.signals.handler(signal) == SIG_DFL || process.signals.is_blocked(signal) {
+            return false;
+        }
+        process.signals.raise(signal);
+        true
+    }
+
+    fn sigaction(
+        &mut self,
+        signal: Signal,
+        handler: VirtAddr,
+        restorer: VirtAddr,
+    ) -> Result<VirtAddr, Errno> {
+        self.current()
+            .and_then(|pid| self.processes.get_mut(&pid))
+            .ok_or(Errno::EINVAL)?
+            .signals
+            .set_handler(signal, handler, restorer)
+    }
+
+    /// Restores the context saved by [`AppManager::deliver_signal`].
+    fn sigreturn(
+        &mut self,
+        frame: &mut TrapFrame,
+        signal_frame: &SignalFrame,
+    ) -> Result<(), Errno> {
+        let process = self
+            .current()
+            .and_then(|pid| self.processes.get_mut(&pid))
+            .ok_or(Errno::EINVAL)?;
+
+        process.signals.set_blocked(signal_frame.blocked);
+        *frame = TrapFrame {
+            // Only the condition flags may be changed, the context has to stay in EL0t.
+            spsr: signal_frame.context.spsr & SPSR_NZCV,
+            ..signal_frame.context
+        };
+        Ok(())
+    }
+
+    /// Delivers a pending signal to the current process, which is about to
+    /// return to EL0 with `frame`.
+    ///
+    /// Returns `false` if the process has been terminated by the signal.
+    fn deliver_signal(&mut self, frame: &mut TrapFrame) -> bool {
+        let Some(pid) = self.current() else {
+            return true;
+        };
+        let Some(process) = self.processes.get_mut(&pid) else {
+            return true;
+        };
+        let Some(signal) = process.signals.take_pending() else {
+            return true;
+        };
+
+        let handler = process.signals.handler(signal);
+        if handler == SIG_DFL {
+            self.terminate(pid, ExitStatus::Signaled(signal));
+            return false;
+        }
+
+        let signal_frame = SignalFrame {
+            context: *frame,
+            blocked: process.signals.block(signal),
+            signal: signal.number() as u64,
+        };
+        let stack_pointer = (frame.sp_el0 as usize).wrapping_sub(size_of::<SignalFrame>()) & !0xF;
+        let bytes = unsafe {
+            core::slice::from_raw_parts(
+                &signal_frame as *const SignalFrame as *const u8,
+                size_of::<SignalFrame>(),
+            )
+        };
+
+        // The stack pointer is up to EL0, the frame must land in its own memory.
+        let written = process.app.as_mut().map(|app| {
+            let address_space = app.address_space_mut();
+            if !address_space.permits_range(stack_pointer, bytes.len(), FaultAccess::Write) {
+                return Err(NovaError::Paging("Signal frame outside of a writable VMA."));
+            }
+            address_space.write(stack_pointer, bytes)
+        });
+        if !matches!(written, Some(Ok(()))) {
+            error!("Unable to deliver {} to process {}", signal, pid);
+            self.terminate(pid, ExitStatus::Signaled(Signal::SIGSEGV));
+            return false;
+        }
+
+        frame.x0 = signal.number() as u64;
+        frame.x30 = process.signals.restorer() as u64;
+        frame.sp_el0 = stack_pointer as u64;
+        frame.elr = handler as u64;
+        true
+    }
+
+    /// Loads the next ready process into `frame`, unless the current one is
+    /// still running, and delivers its pending signals.
+    fn switch(&mut self, frame: &mut TrapFrame) {
+        self.preempt_expired(frame);
+        loop {
+            self.select(frame);
+            if self.deliver_signal(frame) {
+                return;
+            }
+        }
+    }
+
+    /// Requeues the process running on the calling core once its time slice
+    /// is used up, so the other ready processes get their turn.
+    fn preempt_expired(&mut self, frame: &TrapFrame) {
+        if SLICE_TICKS.get().load(Ordering::Relaxed) < TIME_SLICE_TICKS {
+            return;
+        }
+
+        let core = current_core();
+        if self.running[core]
+            .and_then(|pid| self.processes.get(&pid))
+            .is_some_and(|process| {
+                process.state == ProcessState::Running && process.core == Some(core)
+            })
+        {
+            self.yield_current(frame);
+        }
+    }
+
+    fn select(&mut self, frame: &mut TrapFrame) {
+        let core = current_core();
+        if let Some(pid) = self.running[core] {
+            // Once the process blocked or yielded, another core may have picked
+            // it up before this core got here.
+            if self.processes.get(&pid).is_some_and(|process| {
+                process.state == ProcessState::Running && process.core == Some(core)
+            }) {
+                return;
+            }
+        }
+
+        let previous = self.running[core].take();
+
+        while let Some(pid) = self.next_ready(core) {
+            if let Some(process) = self.processes.get_mut(&pid) {
+                if process.state == ProcessState::Ready {
+                    process.activate();
+                    process.state = ProcessState::Running;
+                    process.core = Some(core);
+                    *frame = process.context;
+                    self.running[core] = Some(pid);
+                    SLICE_TICKS.get().store(0, Ordering::Relaxed);
+                    return;
+                }
+            }
+        }
+
+        // The previous context isn't runnable anymore, idle in the kernel loop.
+        if previous.is_some() {
+            set_kernel_ttbr0();
+            set_return_to_kernel_loop(frame);
+        }
     }
 }
 
 unsafe impl Send for AppManager {}
 
+/// Lets the syscall blocked for `reason` in `context` return for a signal.
+///
+/// The syscall is restarted afterwards, only `wait` and sleeps fail with `EINTR`.
+fn interrupt_syscall(context: &mut TrapFrame, reason: WaitReason) {
+    match reason {
+        WaitReason::Process(_) | WaitReason::Sleep(_) => {
+            context.x0 = Errno::EINTR.to_return_value() as u64
+        }
+        // Step back to the `svc` instruction.
+        _ => context.elr -= 4,
+    }
+}
+
+/// Whether `pid` is ready and may run on `core`.
+fn may_run(processes: &BTreeMap<Pid, Process>, pid: Pid, core: usize) -> bool {
+    processes.get(&pid).is_some_and(|process| {
+        process.state == ProcessState::Ready && process.affinity & (1 << core) != 0
+    })
+}
+
+impl AppManager {
+    /// Process running on the calling core.
+    fn current(&self) -> Option<Pid> {
+        self.running[current_core()]
+    }
+
+    /// Queues the ready process `pid` on one of its cores and wakes that core,
+    /// in case it idles.
+    fn enqueue(&mut self, pid: Pid) {
+        let Some(process) = self.processes.get(&pid) else {
+            return;
+        };
+        let core = self.run_queues.enqueue(pid, process.affinity, process.core);
+        if core != current_core() {
+            send_ipi(core, Ipi::Reschedule);
+        }
+    }
+
+    /// Next process queued on `core`, or stolen from another core if it has
+    /// run out of work.
+    fn next_ready(&mut self, core: usize) -> Option<Pid> {
+        if let Some(pid) = self.run_queues.pop(core) {
+            return Some(pid);
+        }
+        let processes = &self.processes;
+        self.run_queues
+            .steal(core, |pid| may_run(processes, pid, core))
+    }
+
+    fn has_ready_processes(&self, core: usize) -> bool {
+        self.run_queues
+            .has_work(core, |pid| may_run(&self.processes, pid, core))
+    }
+
+    /// Restricts the process `pid`, or the current one for `0`, to the cores
+    /// in `mask`.
+    ///
+    /// A queued process moves to an allowed core right away, a running one
+    /// once it is scheduled again.
+    fn set_affinity(&mut self, pid: Pid, mask: CpuMask) -> Result<(), Errno> {
+        let pid = self.resolve_pid(pid)?;
+        let mask = mask & ALL_CORES;
+        if ready_cores_in(mask).next().is_none() {
+            return Err(Errno::EINVAL);
+        }
+
+        let process = self.processes.get_mut(&pid).ok_or(Errno::ESRCH)?;
+        process.affinity = mask;
+        if process.state == ProcessState::Ready {
+            self.run_queues.remove(pid);
+            self.enqueue(pid);
+        }
+        Ok(())
+    }
+
+    fn affinity(&self, pid: Pid) -> Result<CpuMask, Errno> {
+        let pid = self.resolve_pid(pid)?;
+        Ok(self.processes[&pid].affinity)
+    }
+
+    /// Resolves `0` to the current process and rejects terminated ones.
+    fn resolve_pid(&self, pid: Pid) -> Result<Pid, Errno> {
+        let pid = match pid {
+            0 => self.current().ok_or(Errno::ESRCH)?,
+            pid => pid,
+        };
+        self.processes
+            .get(&pid)
+            .filter(|process| !matches!(process.state, ProcessState::Zombie(_)))
+            .map(|_| pid)
+            .ok_or(Errno::ESRCH)
+    }
+}
+
 pub struct Application {
-    pub table_ptr: *mut TableEntry,
+    address_space: AddressSpace,
     pub start_addr: usize,
     pub stack_pointer: usize,
 }
 
 impl Application {
-    pub fn new(start_addr: VirtAddr) -> Self {
-        let physical_addr = reserve_page();
-        let virtual_address =
-            find_free_kerne_page_in_block(APPLICATION_TRANSLATION_TABLE_VA).unwrap();
-
-        map_page(
-            virtual_address,
-            physical_addr,
-            core::ptr::addr_of_mut!(TRANSLATIONTABLE_TTBR1),
-            NORMAL_MEM | WRITABLE,
-        )
-        .unwrap();
-
-        // TODO: Temporary solution, while kernel and app share some memory regions
-        #[allow(static_mut_refs)]
-        unsafe {
-            let table = &mut *(virtual_address as *mut PageTable);
-            table.0 = TRANSLATIONTABLE_TTBR0.0;
-        }
+    /// Creates the address space of an application, including its stack.
+    ///
+    /// The page below the stack stays unmapped, to catch stack overflows.
+    pub fn new(start_addr: VirtAddr) -> Result<Self, NovaError> {
+        let mut address_space = AddressSpace::new()?;
+        address_space.map_lazy(
+            EL0_STACK_BOTTOM,
+            EL0_STACK_SIZE,
+            EL0_ACCESSIBLE | WRITABLE | NORMAL_MEM | PXN | UXN,
+            VmaKind::Stack,
+        )?;
 
-        Self {
-            table_ptr: physical_addr as *mut TableEntry,
+        Ok(Self {
+            address_space,
             start_addr,
             stack_pointer: EL0_STACK_TOP,
-        }
+        })
     }
 
-    pub unsafe fn configure_registers(&self) {
-        asm!("msr ELR_EL1, {}", in(reg) self.start_addr);
-        asm!("msr SPSR_EL1, {0:x}", in(reg) 0);
-        asm!("msr SP_EL0, {0:x}", in(reg) self.stack_pointer);
-        asm!("msr TTBR0_EL1, {}", in(reg) self.table_ptr as usize);
+    /// Builds the registers an application starts with.
+    ///
+    /// `elr` ->  Exception Link Register (starting virtual address)
+    /// `spsr` -> Saved Program State Register (EL0t, interrupts unmasked)
+    /// `sp_el0` -> Stack Pointer Register (virtual_address of stack Pointer)
+    /// `x0`, `x1`, `x2` -> `argc`, `argv` and `envp`
+    pub fn initial_context(&mut self, args: &[&str], env: &[&str]) -> Result<TrapFrame, NovaError> {
+        let layout = self.construct_inital_stack(args, env)?;
+
+        Ok(TrapFrame {
+            x0: layout.argc as u64,
+            x1: layout.argv as u64,
+            x2: layout.envp as u64,
+            sp_el0: layout.stack_pointer as u64,
+            elr: self.start_addr as u64,
+            spsr: 0,
+            ..Default::default()
+        })
     }
 
-    /// Starts an application.
-    ///
-    /// `ELR_EL1` ->  Exception Link Register (starting virtual address)
-    /// `SPSR_EL1` -> Saved Program State Register (settings for `eret` behaviour)
-    /// `SP_EL0` -> Stack Pointer Register (virtual_address of stack Pointer)
-    /// `TTBR0_EL1` -> Translation Table base Register Register
-    pub fn start(&mut self, args: Vec<&str>) {
-        let size = args.len();
-        let argv = self.construct_inital_stack(args);
-        unsafe {
-            self.configure_registers();
-            asm!("", in("x0") size, in("x1") argv);
-            asm!("eret");
-        }
+    /// Duplicates the application, its memory is shared copy-on-write.
+    pub fn fork(&mut self) -> Result<Self, NovaError> {
+        Ok(Self {
+            address_space: self.address_space.fork()?,
+            start_addr: self.start_addr,
+            stack_pointer: self.stack_pointer,
+        })
     }
 
-    /// Initializes the stack based on the System V ABI
-    fn construct_inital_stack(&mut self, args: Vec<&str>) -> usize {
-        let size = args.len();
-        let mut arg_addresses = Vec::with_capacity(size);
+    pub fn address_space_mut(&mut self) -> &mut AddressSpace {
+        &mut self.address_space
+    }
 
-        // Write strings into stack
-        for value in args {
-            self.stack_pointer -= value.len() * mem::size_of::<u8>();
-            let pointer = self.stack_pointer as *mut u8;
-            unsafe { core::ptr::copy(value.as_ptr(), pointer, value.len()) };
-            arg_addresses.push(pointer);
-        }
-        self.stack_pointer = align_down(self.stack_pointer, 16);
+    /// Switches TTBR0 to the translation table of the application.
+    pub fn activate(&self, asid: u16) {
+        set_ttbr0(self.address_space.physical_address(), asid);
+    }
+
+    /// Initializes the stack based on the System V ABI
+    fn construct_inital_stack(&mut self, args: &[&str], env: &[&str]) -> Result<Layout, NovaError> {
+        // Applications aren't loaded from ELF images, so there are no program headers.
+        let auxv = [
+            (AT_PAGESZ, GRANULARITY as u64),
+            (AT_PHDR, 0),
+            (AT_PHNUM, 0),
+            (AT_ENTRY, self.start_addr as u64),
+        ];
 
-        // TODO: Auxiliry vector entry
-        // TODO: Environment pointers
+        let mut random = [0; RANDOM_BYTES];
+        fill_random(&mut random);
 
-        let argv = self.stack_pointer;
+        let mut image = vec![0; required_size(args, env, auxv.len())];
+        let layout =
+            initial_stack::build(&mut image, self.stack_pointer, args, env, &auxv, &random)?;
 
-        // Write argument pointers into stack
-        for addr in arg_addresses {
-            unsafe { write_volatile(self.stack_pointer as *mut *const u8, addr) };
-            self.stack_pointer -= mem::size_of::<*const u8>();
-        }
+        self.address_space.write(layout.stack_pointer, &image)?;
+        self.stack_pointer = layout.stack_pointer;
 
-        argv
+        Ok(layout)
     }
 }
 
-fn align_down(sp: usize, align: usize) -> usize {
-    sp & !(align - 1)
-}
-
-static APP_MANAGER: Mutex<AppManager> = Mutex::new(AppManager::new());
+static APP_MANAGER: IrqSpinLock<AppManager> = IrqSpinLock::new(AppManager::new());
 
 pub fn initialize_app_manager() {
     let mut guard = APP_MANAGER.lock();
     guard.apps = Some(Vec::new());
+    set_tick_handler(count_tick);
+}
+
+/// Tick handler, accounting the time slice of the running process.
+///
+/// The process is preempted once the interrupt returns through [`schedule`].
+fn count_tick() {
+    SLICE_TICKS.get().fetch_add(1, Ordering::Relaxed);
+}
+
+fn wake_sleepers() {
+    APP_MANAGER.lock().wake_sleepers();
 }
 
-pub fn add_app(app: Application) -> Result<(), NovaError> {
+/// Registers an application entry point and returns its app id.
+pub fn add_app(start_addr: VirtAddr) -> Result<usize, NovaError> {
     if let Some(app_list) = APP_MANAGER.lock().apps.as_mut() {
-        app_list.push(app);
-        Ok(())
+        app_list.push(start_addr);
+        Ok(app_list.len() - 1)
     } else {
         Err(NovaError::General("AppManager not initalized."))
     }
 }
 
-pub fn start_app(index: usize, args: Vec<&str>) -> Result<(), NovaError> {
-    if let Some(app) = APP_MANAGER
+/// Starts a new process of the app `index` and returns its PID.
+///
+/// The process is scheduled once the CPU is handed over by the running one.
+pub fn start_app(index: usize, args: Vec<&str>) -> Result<Pid, NovaError> {
+    let result = APP_MANAGER
         .lock()
-        .apps
-        .as_mut()
-        .and_then(|am| am.get_mut(index))
-    {
-        app.start(args);
-        unreachable!()
-    } else {
-        error!("Unable to start app due to invalid App ID.");
-        Err(NovaError::General("Invalid app id."))
+        .spawn(index, args, FileTable::with_console());
+    if let Err(err) = &result {
+        error!("Unable to start app: {:?}", err);
     }
+    result
+}
+
+/// Starts the apps of `commands` connected by pipes, like `app 0 | app 1`,
+/// and returns their PIDs.
+pub fn start_pipeline(commands: Vec<(usize, Vec<&str>)>) -> Result<Vec<Pid>, NovaError> {
+    let result = APP_MANAGER.lock().spawn_pipeline(commands);
+    if let Err(err) = &result {
+        error!("Unable to start pipeline: {:?}", err);
+    }
+    result
+}
+
+/// Terminates the process `pid`.
+pub fn kill(pid: Pid) -> Result<(), NovaError> {
+    let mut manager = APP_MANAGER.lock();
+    match manager.processes.get(&pid).map(|process| process.state) {
+        None => Err(NovaError::General("No such process.")),
+        Some(ProcessState::Zombie(_)) => Err(NovaError::General("Process already terminated.")),
+        Some(_) => {
+            manager.terminate(pid, ExitStatus::Signaled(Signal::SIGKILL));
+            Ok(())
+        }
+    }
+}
+
+/// Duplicates the current process and returns the PID of the child, see [`AppManager::fork`].
+pub fn fork(frame: &TrapFrame) -> Result<Pid, NovaError> {
+    APP_MANAGER.lock().fork(frame)
+}
+
+/// Replaces the program of the current process, see [`AppManager::exec`].
+pub fn exec(frame: &mut TrapFrame, app_id: usize, args: &[&str]) -> Result<(), NovaError> {
+    APP_MANAGER.lock().exec(frame, app_id, args)
+}
+
+/// Creates a channel owned by the current process and returns its id.
+pub fn create_channel() -> Result<ChannelId, Errno> {
+    APP_MANAGER.lock().create_channel()
+}
+
+/// Closes a channel of the current process, blocked receivers fail with `EBADF`.
+pub fn close_channel(id: ChannelId) -> Result<(), Errno> {
+    APP_MANAGER.lock().close_channel(id)
+}
+
+/// Queues a message on a channel, see [`AppManager::send`].
+pub fn send(id: ChannelId, data: &[u8], page: Option<VirtAddr>) -> Result<(), Errno> {
+    APP_MANAGER.lock().send(id, data, page)
+}
+
+/// Takes a message from a channel, see [`AppManager::receive`].
+pub fn receive(
+    frame: &TrapFrame,
+    id: ChannelId,
+    nonblocking: bool,
+    page_flags: u64,
+) -> Result<Option<(Message, Option<VirtAddr>)>, Errno> {
+    APP_MANAGER
+        .lock()
+        .receive(frame, id, nonblocking, page_flags)
+}
+
+/// Creates a shared memory object owned by the current process and returns its id.
+pub fn create_shared_memory(
+    name: Option<String>,
+    size_bytes: usize,
+) -> Result<SharedMemoryId, Errno> {
+    APP_MANAGER.lock().create_shared_memory(name, size_bytes)
+}
+
+/// Looks up the shared memory object called `name`.
+pub fn open_shared_memory(name: &str) -> Result<SharedMemoryId, Errno> {
+    APP_MANAGER.lock().shared_memory.open(name)
+}
+
+/// Maps a shared memory object into the current process, see [`AddressSpace::map_shared`].
+pub fn map_shared_memory(id: SharedMemoryId, flags: u64) -> Result<VirtAddr, Errno> {
+    APP_MANAGER.lock().map_shared_memory(id, flags)
+}
+
+/// Closes a shared memory object of the current process, its frames are freed
+/// with the last mapping.
+pub fn close_shared_memory(id: SharedMemoryId) -> Result<(), Errno> {
+    APP_MANAGER.lock().close_shared_memory(id)
+}
+
+/// Reads from a file of the current process, see [`AppManager::read`].
+pub fn read(frame: &TrapFrame, fd: Fd, buffer: &mut [u8]) -> Result<Option<usize>, Errno> {
+    APP_MANAGER.lock().read(frame, fd, buffer)
+}
+
+/// Writes to a file of the current process, see [`AppManager::write`].
+pub fn write(frame: &TrapFrame, fd: Fd, data: &[u8]) -> Result<Option<usize>, Errno> {
+    APP_MANAGER.lock().write(frame, fd, data)
+}
+
+pub fn close(fd: Fd) -> Result<(), Errno> {
+    APP_MANAGER.lock().close(fd)
+}
+
+/// Duplicates a file descriptor of the current process, see [`AppManager::dup2`].
+pub fn dup2(old_fd: Fd, new_fd: Fd) -> Result<Fd, Errno> {
+    APP_MANAGER.lock().dup2(old_fd, new_fd)
+}
+
+/// Creates a pipe for the current process, see [`AppManager::pipe`].
+pub fn pipe() -> Result<(Fd, Fd), Errno> {
+    APP_MANAGER.lock().pipe()
+}
+
+/// Hands a byte typed into the terminal to the processes reading the console.
+///
+/// Returns `false` if no process is reading, the input then belongs to the terminal.
+pub fn push_console_input(byte: u8) -> bool {
+    let mut manager = APP_MANAGER.lock();
+    if !manager.awaits_console_input() {
+        return false;
+    }
+    manager.push_console_input(byte);
+    true
+}
+
+/// Sends a signal to a process, see [`AppManager::send_signal`].
+pub fn send_signal(pid: Pid, signal: Signal) -> Result<(), Errno> {
+    APP_MANAGER.lock().send_signal(pid, signal)
+}
+
+/// Raises the signal of a fault for the current process, see [`AppManager::raise_fault_signal`].
+pub fn raise_fault_signal(signal: Signal) -> bool {
+    APP_MANAGER.lock().raise_fault_signal(signal)
+}
+
+/// Registers a signal handler of the current process and returns the previous one.
+pub fn sigaction(signal: Signal, handler: VirtAddr, restorer: VirtAddr) -> Result<VirtAddr, Errno> {
+    APP_MANAGER.lock().sigaction(signal, handler, restorer)
+}
+
+/// Returns from a signal handler, see [`AppManager::sigreturn`].
+pub fn sigreturn(frame: &mut TrapFrame, signal_frame: &SignalFrame) -> Result<(), Errno> {
+    APP_MANAGER.lock().sigreturn(frame, signal_frame)
+}
+
+/// Blocks the current process until the system timer reaches `deadline_us`.
+///
+/// Returns `false` without blocking if the deadline has already passed.
+pub fn sleep_until(frame: &TrapFrame, deadline_us: u64) -> bool {
+    APP_MANAGER.lock().sleep_until(frame, deadline_us)
+}
+
+/// Terminates the current process.
+pub fn exit_current(status: ExitStatus) {
+    let mut manager = APP_MANAGER.lock();
+    if let Some(pid) = manager.current() {
+        manager.terminate(pid, status);
+    }
+}
+
+/// Collects the exit status of `pid` for the current process, see [`AppManager::wait`].
+pub fn wait(frame: &TrapFrame, pid: Pid) -> Result<Option<ExitStatus>, Errno> {
+    APP_MANAGER.lock().wait(frame, pid)
+}
+
+/// Moves the current process to the back of the run queue and switches to the
+/// next ready process.
+///
+/// Both happen under one lock, so no other core picks up the process before
+/// this core has left it.
+pub fn yield_current(frame: &mut TrapFrame) {
+    let mut manager = APP_MANAGER.lock();
+    manager.yield_current(frame);
+    manager.switch(frame);
+}
+
+pub fn current_pid() -> Option<Pid> {
+    APP_MANAGER.lock().current()
+}
+
+/// Runs `f` on the address space of the current process.
+///
+/// Returns `None` if no process is running.
+pub fn with_current_address_space<R>(f: impl FnOnce(&mut AddressSpace) -> R) -> Option<R> {
+    let mut manager = APP_MANAGER.lock();
+    let app = manager.current_app()?;
+    Some(f(app.address_space_mut()))
+}
+
+/// Resolves a page fault at `address` of the current process, see [`AddressSpace::handle_fault`].
+pub fn handle_page_fault(address: VirtAddr, access: FaultAccess) -> Result<(), NovaError> {
+    with_current_address_space(|space| space.handle_fault(address, access))
+        .unwrap_or(Err(NovaError::General("No process running.")))
+}
+
+/// App id of the current process.
+pub fn current_app_id() -> Option<usize> {
+    let manager = APP_MANAGER.lock();
+    manager
+        .current()
+        .and_then(|pid| manager.processes.get(&pid))
+        .map(|process| process.app_id)
+}
+
+/// Decides which context an exception returns to.
+///
+/// Scheduling is cooperative: a running process keeps the CPU until it exits,
+/// blocks or yields. `frame` is then replaced by the context of the next ready
+/// process, or by the kernel loop if none is ready.
+pub fn schedule(frame: &mut TrapFrame) {
+    APP_MANAGER.lock().switch(frame);
+}
+
+/// Whether the calling core has a process to run.
+pub fn has_ready_processes() -> bool {
+    APP_MANAGER.lock().has_ready_processes(current_core())
+}
+
+/// Restricts the process `pid`, or the current one for `0`, to the cores in `mask`.
+pub fn set_affinity(pid: Pid, mask: CpuMask) -> Result<(), Errno> {
+    APP_MANAGER.lock().set_affinity(pid, mask)
+}
+
+/// Cores the process `pid`, or the current one for `0`, may run on.
+pub fn affinity(pid: Pid) -> Result<CpuMask, Errno> {
+    APP_MANAGER.lock().affinity(pid)
+}
+
+/// Hands the CPU from the kernel loop to the ready processes.
+pub fn run_ready_processes() {
+    unsafe { asm!("svc #0") };
+}
+
+pub fn processes() -> Vec<ProcessInfo> {
+    APP_MANAGER
+        .lock()
+        .processes
+        .values()
+        .map(|process| ProcessInfo {
+            pid: process.pid,
+            parent: process.parent,
+            app_id: process.app_id,
+            state: process.state,
+            core: process
+                .core
+                .filter(|_| process.state == ProcessState::Running),
+        })
+        .collect()
 }
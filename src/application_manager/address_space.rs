@@ -0,0 +1,494 @@
+use core::cmp::{max, min};
+
+use alloc::{collections::btree_map::BTreeMap, vec::Vec};
+use nova_error::NovaError;
+
+use crate::{
+    aarch64::mmu::{
+        allocate_frame, for_each_private_page, frame_address, map_page, page_entry,
+        physical_mapping::frame_references, release_address_space, release_frame, remap_page,
+        share_frame, translate, unmap_page, PageTable, PhysAddr, TableEntry, VirtAddr,
+        EL0_ACCESSIBLE, GRANULARITY, NON_GLOBAL, READ_ONLY, TRANSLATIONTABLE_TTBR0, UXN,
+    },
+    configuration::memory_mapping::{USER_HEAP_BASE, USER_HEAP_END, USER_MMAP_BASE, USER_MMAP_END},
+};
+
+/// Purpose of a [`Vma`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VmaKind {
+    Stack,
+    /// Memory below the program break.
+    Heap,
+    /// Mapping created by `mmap`.
+    Anonymous,
+    /// Mapping of a shared memory object, backed when it is created.
+    Shared,
+}
+
+/// Virtual memory area, a range of pages which are backed by zeroed frames
+/// on their first access, unless it maps shared memory.
+#[derive(Debug, Clone, Copy)]
+pub struct Vma {
+    pub start: VirtAddr,
+    pub end: VirtAddr,
+    /// Translation table flags of the pages.
+    pub flags: u64,
+    pub kind: VmaKind,
+}
+
+impl Vma {
+    /// Whether the pages of the area permit `access` from EL0.
+    fn permits(&self, access: FaultAccess) -> bool {
+        self.flags & EL0_ACCESSIBLE != 0
+            && match access {
+                FaultAccess::Read => true,
+                FaultAccess::Write => self.flags & READ_ONLY == 0,
+                FaultAccess::Execute => self.flags & UXN == 0,
+            }
+    }
+}
+
+/// Access which caused a page fault.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultAccess {
+    Read,
+    Write,
+    Execute,
+}
+
+/// Translation tables of an application.
+///
+/// Mappings added to the address space are private to it, everything else is
+/// shared with the kernel's TTBR0 table. Private memory is described by VMAs
+/// and only backed by frames once it is touched.
+pub struct AddressSpace {
+    root_physical_address: PhysAddr,
+    /// Current program break, the heap spans from `USER_HEAP_BASE` up to it.
+    program_break: VirtAddr,
+    /// Memory areas, indexed by their start address.
+    vmas: BTreeMap<VirtAddr, Vma>,
+}
+
+impl AddressSpace {
+    pub fn new() -> Result<Self, NovaError> {
+        let root_physical_address = allocate_frame()?;
+
+        // TODO: Temporary solution, while kernel and app share some memory regions
+        #[allow(static_mut_refs)]
+        unsafe {
+            let table = &mut *(frame_address(root_physical_address) as *mut PageTable);
+            table.0 = TRANSLATIONTABLE_TTBR0.0;
+        }
+
+        Ok(Self {
+            root_physical_address,
+            program_break: USER_HEAP_BASE,
+            vmas: BTreeMap::new(),
+        })
+    }
+
+    /// Physical address of the root table, as loaded into TTBR0.
+    pub fn physical_address(&self) -> PhysAddr {
+        self.root_physical_address
+    }
+
+    fn table(&self) -> *mut PageTable {
+        frame_address(self.root_physical_address) as *mut PageTable
+    }
+
+    /// Adds a VMA of `size_bytes` starting at `virtual_address`.
+    ///
+    /// No frames are allocated, until the pages are touched.
+    pub fn map_lazy(
+        &mut self,
+        virtual_address: VirtAddr,
+        size_bytes: usize,
+        flags: u64,
+        kind: VmaKind,
+    ) -> Result<(), NovaError> {
+        if !virtual_address.is_multiple_of(GRANULARITY) {
+            return Err(NovaError::Misalignment);
+        }
+        if !size_bytes.is_multiple_of(GRANULARITY) {
+            return Err(NovaError::InvalidGranularity);
+        }
+
+        let end = virtual_address + size_bytes;
+        if self
+            .vmas
+            .range(..end)
+            .any(|(_, vma)| vma.end > virtual_address)
+        {
+            return Err(NovaError::Paging("VMA overlaps an existing one."));
+        }
+
+        self.vmas.insert(
+            virtual_address,
+            Vma {
+                start: virtual_address,
+                end,
+                flags,
+                kind,
+            },
+        );
+        Ok(())
+    }
+
+    /// VMA containing `address`.
+    pub fn find_vma(&self, address: VirtAddr) -> Option<&Vma> {
+        self.vmas
+            .range(..=address)
+            .next_back()
+            .map(|(_, vma)| vma)
+            .filter(|vma| address < vma.end)
+    }
+
+    /// Whether `len` bytes at `address` lie within a single VMA permitting
+    /// `access`.
+    pub fn permits_range(&self, address: VirtAddr, len: usize, access: FaultAccess) -> bool {
+        let Some(end) = address.checked_add(len) else {
+            return false;
+        };
+        self.find_vma(address)
+            .is_some_and(|vma| end <= vma.end && vma.permits(access))
+    }
+
+    /// Resolves a fault on `address`, if `access` is permitted by its VMA.
+    ///
+    /// Pages are backed with a zeroed frame on their first access, writes to
+    /// pages shared by [`AddressSpace::fork`] get a private copy.
+    pub fn handle_fault(
+        &mut self,
+        address: VirtAddr,
+        access: FaultAccess,
+    ) -> Result<(), NovaError> {
+        let vma = *self
+            .find_vma(address)
+            .ok_or(NovaError::Paging("Address outside of any VMA."))?;
+
+        if !vma.permits(access) {
+            return Err(NovaError::Paging("Access not permitted by VMA."));
+        }
+
+        let page = address & !(GRANULARITY - 1);
+        match page_entry(page, self.table()) {
+            Ok(entry) if access == FaultAccess::Write && entry.is_read_only() => {
+                self.copy_on_write(page, entry)
+            }
+            Ok(_) => Err(NovaError::Paging("Page is already mapped.")),
+            Err(_) => self.populate(page, vma.flags),
+        }
+    }
+
+    /// Makes a page shared by [`AddressSpace::fork`] writable again.
+    ///
+    /// The frame is copied, unless this address space holds its last reference.
+    fn copy_on_write(&mut self, page: VirtAddr, entry: TableEntry) -> Result<(), NovaError> {
+        let shared_frame = entry.address();
+        let flags = entry.page_flags() & !READ_ONLY;
+
+        if frame_references(shared_frame) <= 1 {
+            return remap_page(page, shared_frame, self.table(), flags);
+        }
+
+        let frame = copy_frame(shared_frame)?;
+        if let Err(err) = remap_page(page, frame, self.table(), flags) {
+            release_frame(frame);
+            return Err(err);
+        }
+        release_frame(shared_frame);
+        Ok(())
+    }
+
+    /// Duplicates the address space, sharing all backed pages copy-on-write.
+    ///
+    /// Writable pages become read-only in both address spaces, so stale TLB
+    /// entries of this address space have to be invalidated afterwards.
+    pub fn fork(&mut self) -> Result<AddressSpace, NovaError> {
+        let mut child = AddressSpace::new()?;
+        child.program_break = self.program_break;
+        child.vmas = self.vmas.clone();
+
+        let child_table = child.table();
+        let mut result = Ok(());
+        for_each_private_page(self.root_physical_address, |virtual_address, entry| {
+            if result.is_err() {
+                return;
+            }
+
+            // Shared memory stays shared, instead of being copied on write.
+            if child
+                .find_vma(virtual_address)
+                .is_none_or(|vma| vma.kind != VmaKind::Shared)
+            {
+                entry.set_read_only();
+            }
+            result = share_frame(entry.address()).and_then(|_| {
+                map_page(
+                    virtual_address,
+                    entry.address(),
+                    child_table,
+                    entry.page_flags(),
+                )
+                .inspect_err(|_| release_frame(entry.address()))
+            });
+        });
+
+        result.map(|_| child)
+    }
+
+    /// Maps a zeroed frame to `page`.
+    fn populate(&mut self, page: VirtAddr, flags: u64) -> Result<(), NovaError> {
+        let frame = allocate_frame()?;
+        map_page(page, frame, self.table(), flags | NON_GLOBAL)
+            .inspect_err(|_| release_frame(frame))
+    }
+
+    /// Unmaps `size_bytes` starting at `virtual_address` and releases the frames.
+    ///
+    /// Pages which aren't backed are skipped.
+    fn unmap(&mut self, virtual_address: VirtAddr, size_bytes: usize) {
+        for page in (virtual_address..virtual_address + size_bytes).step_by(GRANULARITY) {
+            if let Ok(frame) = unmap_page(page, self.table()) {
+                release_frame(frame);
+            }
+        }
+    }
+
+    /// Creates an anonymous mapping of `size_bytes` and returns its address.
+    ///
+    /// A `fixed_address` replaces whatever is mapped there, otherwise the first
+    /// free range of the mmap area is used.
+    pub fn mmap(
+        &mut self,
+        fixed_address: Option<VirtAddr>,
+        size_bytes: usize,
+        flags: u64,
+    ) -> Result<VirtAddr, NovaError> {
+        let start = match fixed_address {
+            Some(address) => {
+                if address < USER_MMAP_BASE || address + size_bytes > USER_MMAP_END {
+                    return Err(NovaError::General("Fixed mapping outside of mmap area."));
+                }
+                self.munmap(address, size_bytes);
+                address
+            }
+            None => self
+                .find_free_range(size_bytes)
+                .ok_or(NovaError::OutOfMeomory)?,
+        };
+
+        self.map_lazy(start, size_bytes, flags, VmaKind::Anonymous)?;
+        Ok(start)
+    }
+
+    /// Removes the parts of mmap and shared memory mappings within `size_bytes`
+    /// starting at `virtual_address`.
+    pub fn munmap(&mut self, virtual_address: VirtAddr, size_bytes: usize) {
+        let end = virtual_address + size_bytes;
+        let overlapping: Vec<Vma> = self
+            .vmas
+            .range(..end)
+            .map(|(_, vma)| *vma)
+            .filter(|vma| {
+                vma.end > virtual_address
+                    && matches!(vma.kind, VmaKind::Anonymous | VmaKind::Shared)
+            })
+            .collect();
+
+        for vma in overlapping {
+            self.vmas.remove(&vma.start);
+            if vma.start < virtual_address {
+                self.vmas.insert(
+                    vma.start,
+                    Vma {
+                        end: virtual_address,
+                        ..vma
+                    },
+                );
+            }
+            if vma.end > end {
+                self.vmas.insert(end, Vma { start: end, ..vma });
+            }
+
+            let unmap_start = max(vma.start, virtual_address);
+            self.unmap(unmap_start, min(vma.end, end) - unmap_start);
+        }
+    }
+
+    /// Removes the mmap page at `page` from the address space and returns the
+    /// frame backing it, together with its reference.
+    ///
+    /// A frame shared with another address space is copied first, so the
+    /// caller owns it exclusively.
+    pub fn take_page(&mut self, page: VirtAddr) -> Result<PhysAddr, NovaError> {
+        if !page.is_multiple_of(GRANULARITY) {
+            return Err(NovaError::Misalignment);
+        }
+        let vma = *self
+            .find_vma(page)
+            .filter(|vma| vma.kind == VmaKind::Anonymous)
+            .ok_or(NovaError::Paging("Page isn't part of an mmap mapping."))?;
+
+        if translate(page, self.table()).is_err() {
+            self.populate(page, vma.flags)?;
+        }
+        let frame = unmap_page(page, self.table())?;
+        // Only splits the VMA, the page isn't mapped anymore.
+        self.munmap(page, GRANULARITY);
+
+        if frame_references(frame) <= 1 {
+            return Ok(frame);
+        }
+        let copy = copy_frame(frame);
+        release_frame(frame);
+        copy
+    }
+
+    /// Maps `frame` into the mmap area and returns its address.
+    ///
+    /// The reference to the frame is handed over to the address space.
+    pub fn map_frame(&mut self, frame: PhysAddr, flags: u64) -> Result<VirtAddr, NovaError> {
+        let page = self
+            .find_free_range(GRANULARITY)
+            .ok_or(NovaError::OutOfMeomory)?;
+
+        self.map_lazy(page, GRANULARITY, flags, VmaKind::Anonymous)?;
+        if let Err(err) = map_page(page, frame, self.table(), flags | NON_GLOBAL) {
+            self.vmas.remove(&page);
+            return Err(err);
+        }
+        Ok(page)
+    }
+
+    /// Maps `frames` contiguously into the mmap area and returns the address of
+    /// the first one.
+    ///
+    /// Each mapped frame gets an additional reference, which is released when
+    /// the mapping is removed.
+    pub fn map_shared(&mut self, frames: &[PhysAddr], flags: u64) -> Result<VirtAddr, NovaError> {
+        let size_bytes = frames.len() * GRANULARITY;
+        let start = self
+            .find_free_range(size_bytes)
+            .ok_or(NovaError::OutOfMeomory)?;
+        self.map_lazy(start, size_bytes, flags, VmaKind::Shared)?;
+
+        for (index, frame) in frames.iter().enumerate() {
+            if let Err(err) = share_frame(*frame) {
+                self.munmap(start, size_bytes);
+                return Err(err);
+            }
+            if let Err(err) = map_page(
+                start + index * GRANULARITY,
+                *frame,
+                self.table(),
+                flags | NON_GLOBAL,
+            ) {
+                release_frame(*frame);
+                self.munmap(start, size_bytes);
+                return Err(err);
+            }
+        }
+        Ok(start)
+    }
+
+    /// Lowest range of `size_bytes` in the mmap area, not overlapping any VMA.
+    fn find_free_range(&self, size_bytes: usize) -> Option<VirtAddr> {
+        let mut candidate = USER_MMAP_BASE;
+        for vma in self
+            .vmas
+            .range(USER_MMAP_BASE..USER_MMAP_END)
+            .map(|(_, vma)| vma)
+        {
+            if candidate + size_bytes <= vma.start {
+                break;
+            }
+            candidate = max(candidate, vma.end);
+        }
+        (candidate + size_bytes <= USER_MMAP_END).then_some(candidate)
+    }
+
+    /// Moves the program break to `new_break` and returns the resulting break.
+    ///
+    /// The break stays unchanged if `new_break` is out of range.
+    pub fn brk(&mut self, new_break: VirtAddr, flags: u64) -> VirtAddr {
+        if !(USER_HEAP_BASE..=USER_HEAP_END).contains(&new_break) {
+            return self.program_break;
+        }
+
+        let mapped_end = self.program_break.next_multiple_of(GRANULARITY);
+        let new_end = new_break.next_multiple_of(GRANULARITY);
+
+        if new_end < mapped_end {
+            self.unmap(new_end, mapped_end - new_end);
+        }
+
+        if new_end == USER_HEAP_BASE {
+            self.vmas.remove(&USER_HEAP_BASE);
+        } else {
+            self.vmas.insert(
+                USER_HEAP_BASE,
+                Vma {
+                    start: USER_HEAP_BASE,
+                    end: new_end,
+                    flags,
+                    kind: VmaKind::Heap,
+                },
+            );
+        }
+
+        self.program_break = new_break;
+        new_break
+    }
+
+    /// Copies `data` to `virtual_address`, without the address space being active.
+    pub fn write(&mut self, virtual_address: VirtAddr, data: &[u8]) -> Result<(), NovaError> {
+        let mut written = 0;
+        while written < data.len() {
+            let address = virtual_address + written;
+            let length = min(GRANULARITY - address % GRANULARITY, data.len() - written);
+            let target = frame_address(self.backing_frame(address)?);
+
+            unsafe {
+                core::ptr::copy_nonoverlapping(data[written..].as_ptr(), target as *mut u8, length)
+            };
+            written += length;
+        }
+        Ok(())
+    }
+
+    /// Physical address `address` is mapped to, resolving a write fault first
+    /// if the page isn't backed or shared copy-on-write.
+    ///
+    /// Only addresses within a writable VMA are accepted, the pages shared
+    /// with the kernel aren't reachable through [`frame_address`].
+    fn backing_frame(&mut self, address: VirtAddr) -> Result<PhysAddr, NovaError> {
+        if !self.permits_range(address, 1, FaultAccess::Write) {
+            return Err(NovaError::Paging("Address outside of any writable VMA."));
+        }
+        match page_entry(address & !(GRANULARITY - 1), self.table()) {
+            Ok(entry) if !entry.is_read_only() => {}
+            _ => self.handle_fault(address, FaultAccess::Write)?,
+        }
+        translate(address, self.table())
+    }
+}
+
+/// Allocates a frame holding a copy of the frame at `source`.
+fn copy_frame(source: PhysAddr) -> Result<PhysAddr, NovaError> {
+    let frame = allocate_frame()?;
+    unsafe {
+        core::ptr::copy_nonoverlapping(
+            frame_address(source) as *const u8,
+            frame_address(frame) as *mut u8,
+            GRANULARITY,
+        )
+    };
+    Ok(frame)
+}
+
+impl Drop for AddressSpace {
+    fn drop(&mut self) {
+        release_address_space(self.root_physical_address);
+    }
+}
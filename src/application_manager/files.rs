@@ -0,0 +1,202 @@
+//! File descriptors and pipes.
+//!
+//! Every process has a table of file descriptors, which refer to the console
+//! or to one end of a pipe. Pipes count the descriptors referring to each of
+//! their ends, a pipe is freed once both counts reach zero.
+
+use alloc::collections::{btree_map::BTreeMap, vec_deque::VecDeque};
+
+use crate::syscalls::Errno;
+
+pub type Fd = usize;
+pub type PipeId = usize;
+
+/// Number of file descriptors per process.
+pub const MAX_FILES: usize = 16;
+
+/// Bytes a pipe buffers, before writes block.
+pub const PIPE_CAPACITY: usize = 4096;
+
+pub const STDIN: Fd = 0;
+pub const STDOUT: Fd = 1;
+pub const STDERR: Fd = 2;
+
+/// Object a file descriptor refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum File {
+    /// UART console, reads return the input typed into the terminal.
+    Console,
+    PipeReader(PipeId),
+    PipeWriter(PipeId),
+}
+
+/// File descriptors of a process.
+///
+/// Copying a table doesn't acquire the pipe ends it refers to, see [`Pipes::acquire`].
+#[derive(Debug, Clone)]
+pub struct FileTable {
+    files: [Option<File>; MAX_FILES],
+}
+
+impl FileTable {
+    pub const fn empty() -> Self {
+        Self {
+            files: [None; MAX_FILES],
+        }
+    }
+
+    /// Table with stdin, stdout and stderr connected to the console.
+    pub fn with_console() -> Self {
+        Self::with_stdio(File::Console, File::Console)
+    }
+
+    /// Table with the given stdin and stdout, stderr is connected to the console.
+    pub fn with_stdio(stdin: File, stdout: File) -> Self {
+        let mut table = Self::empty();
+        table.files[STDIN] = Some(stdin);
+        table.files[STDOUT] = Some(stdout);
+        table.files[STDERR] = Some(File::Console);
+        table
+    }
+
+    pub fn get(&self, fd: Fd) -> Result<File, Errno> {
+        self.files.get(fd).copied().flatten().ok_or(Errno::EBADF)
+    }
+
+    /// Stores `file` at the lowest free descriptor and returns it.
+    pub fn insert(&mut self, file: File) -> Result<Fd, Errno> {
+        let fd = self
+            .files
+            .iter()
+            .position(Option::is_none)
+            .ok_or(Errno::EMFILE)?;
+        self.files[fd] = Some(file);
+        Ok(fd)
+    }
+
+    /// Stores `file` at `fd` and returns the file it replaces.
+    pub fn replace(&mut self, fd: Fd, file: File) -> Result<Option<File>, Errno> {
+        let slot = self.files.get_mut(fd).ok_or(Errno::EBADF)?;
+        Ok(slot.replace(file))
+    }
+
+    pub fn remove(&mut self, fd: Fd) -> Result<File, Errno> {
+        self.files
+            .get_mut(fd)
+            .and_then(Option::take)
+            .ok_or(Errno::EBADF)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = File> + '_ {
+        self.files.iter().flatten().copied()
+    }
+}
+
+struct Pipe {
+    buffer: VecDeque<u8>,
+    readers: usize,
+    writers: usize,
+}
+
+pub(super) struct Pipes {
+    pipes: BTreeMap<PipeId, Pipe>,
+    next_id: PipeId,
+}
+
+impl Pipes {
+    pub const fn new() -> Self {
+        Self {
+            pipes: BTreeMap::new(),
+            next_id: 1,
+        }
+    }
+
+    /// Creates a pipe and returns its read and write end, each referenced once.
+    pub fn create(&mut self) -> (File, File) {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.pipes.insert(
+            id,
+            Pipe {
+                buffer: VecDeque::new(),
+                readers: 1,
+                writers: 1,
+            },
+        );
+        (File::PipeReader(id), File::PipeWriter(id))
+    }
+
+    /// Adds a reference to `file`, for a new descriptor referring to it.
+    pub fn acquire(&mut self, file: File) {
+        match file {
+            File::Console => {}
+            File::PipeReader(id) => {
+                if let Some(pipe) = self.pipes.get_mut(&id) {
+                    pipe.readers += 1;
+                }
+            }
+            File::PipeWriter(id) => {
+                if let Some(pipe) = self.pipes.get_mut(&id) {
+                    pipe.writers += 1;
+                }
+            }
+        }
+    }
+
+    /// Drops a reference to `file` and returns the pipe it belongs to, whose
+    /// blocked readers or writers may now have to be woken.
+    pub fn release(&mut self, file: File) -> Option<PipeId> {
+        let (id, reader) = match file {
+            File::Console => return None,
+            File::PipeReader(id) => (id, true),
+            File::PipeWriter(id) => (id, false),
+        };
+
+        let pipe = self.pipes.get_mut(&id)?;
+        if reader {
+            pipe.readers = pipe.readers.saturating_sub(1);
+        } else {
+            pipe.writers = pipe.writers.saturating_sub(1);
+        }
+        if pipe.readers == 0 && pipe.writers == 0 {
+            self.pipes.remove(&id);
+        }
+        Some(id)
+    }
+
+    /// Reads from the pipe `id` into `buffer`.
+    ///
+    /// Returns `0` at the end of file, once every write end has been closed,
+    /// or `None` if the pipe is empty.
+    pub fn read(&mut self, id: PipeId, buffer: &mut [u8]) -> Result<Option<usize>, Errno> {
+        let pipe = self.pipes.get_mut(&id).ok_or(Errno::EBADF)?;
+        if pipe.buffer.is_empty() {
+            return Ok((pipe.writers == 0).then_some(0));
+        }
+
+        let count = buffer.len().min(pipe.buffer.len());
+        for (byte, value) in buffer.iter_mut().zip(pipe.buffer.drain(..count)) {
+            *byte = value;
+        }
+        Ok(Some(count))
+    }
+
+    /// Writes as much of `data` as fits into the pipe `id`.
+    ///
+    /// Fails with `EPIPE` once every read end has been closed, returns `None`
+    /// if the pipe is full.
+    pub fn write(&mut self, id: PipeId, data: &[u8]) -> Result<Option<usize>, Errno> {
+        let pipe = self.pipes.get_mut(&id).ok_or(Errno::EBADF)?;
+        if pipe.readers == 0 {
+            return Err(Errno::EPIPE);
+        }
+
+        let count = data.len().min(PIPE_CAPACITY - pipe.buffer.len());
+        if count == 0 && !data.is_empty() {
+            return Ok(None);
+        }
+        pipe.buffer.extend(&data[..count]);
+        Ok(Some(count))
+    }
+}
@@ -0,0 +1,130 @@
+//! Message channels between processes.
+//!
+//! Channels are addressed by global ids, so any process knowing the id of a
+//! channel may send to it or receive from it. Only the process which created
+//! a channel may close it, which happens implicitly once that process terminates.
+
+use alloc::{
+    collections::{btree_map::BTreeMap, vec_deque::VecDeque},
+    vec::Vec,
+};
+
+use crate::{
+    aarch64::mmu::{release_frame, PhysAddr},
+    application_manager::process::Pid,
+    syscalls::Errno,
+};
+
+pub type ChannelId = usize;
+
+/// Upper bound of the payload of a message in bytes.
+pub const MESSAGE_SIZE: usize = 64;
+
+/// Number of messages a channel queues, before sends fail.
+const CHANNEL_CAPACITY: usize = 16;
+
+/// Message queued in a channel.
+pub struct Message {
+    pub data: [u8; MESSAGE_SIZE],
+    pub len: usize,
+    /// Frame transferred with the message, owned by the message until it is
+    /// mapped into the receiver.
+    pub frame: Option<PhysAddr>,
+}
+
+impl Drop for Message {
+    fn drop(&mut self) {
+        if let Some(frame) = self.frame.take() {
+            release_frame(frame);
+        }
+    }
+}
+
+struct Channel {
+    owner: Pid,
+    queue: VecDeque<Message>,
+}
+
+pub(super) struct Channels {
+    channels: BTreeMap<ChannelId, Channel>,
+    next_id: ChannelId,
+}
+
+impl Channels {
+    pub const fn new() -> Self {
+        Self {
+            channels: BTreeMap::new(),
+            next_id: 1,
+        }
+    }
+
+    pub fn create(&mut self, owner: Pid) -> ChannelId {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.channels.insert(
+            id,
+            Channel {
+                owner,
+                queue: VecDeque::new(),
+            },
+        );
+        id
+    }
+
+    /// Closes the channel `id` on behalf of `pid`, queued messages are dropped.
+    pub fn close(&mut self, id: ChannelId, pid: Pid) -> Result<(), Errno> {
+        match self.channels.get(&id) {
+            None => Err(Errno::EBADF),
+            Some(channel) if channel.owner != pid => Err(Errno::EPERM),
+            Some(_) => {
+                self.channels.remove(&id);
+                Ok(())
+            }
+        }
+    }
+
+    /// Closes every channel owned by `owner` and returns their ids.
+    pub fn close_owned_by(&mut self, owner: Pid) -> Vec<ChannelId> {
+        let owned: Vec<ChannelId> = self
+            .channels
+            .iter()
+            .filter(|(_, channel)| channel.owner == owner)
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in &owned {
+            self.channels.remove(id);
+        }
+        owned
+    }
+
+    /// Fails with `EAGAIN` if the channel `id` can't queue another message.
+    pub fn reserve(&self, id: ChannelId) -> Result<(), Errno> {
+        let channel = self.channels.get(&id).ok_or(Errno::EBADF)?;
+        if channel.queue.len() >= CHANNEL_CAPACITY {
+            return Err(Errno::EAGAIN);
+        }
+        Ok(())
+    }
+
+    pub fn push(&mut self, id: ChannelId, message: Message) -> Result<(), Errno> {
+        self.reserve(id)?;
+        self.channels
+            .get_mut(&id)
+            .ok_or(Errno::EBADF)?
+            .queue
+            .push_back(message);
+        Ok(())
+    }
+
+    /// Takes the oldest message of the channel `id`, if there is one.
+    pub fn pop(&mut self, id: ChannelId) -> Result<Option<Message>, Errno> {
+        Ok(self
+            .channels
+            .get_mut(&id)
+            .ok_or(Errno::EBADF)?
+            .queue
+            .pop_front())
+    }
+}
@@ -0,0 +1,170 @@
+use core::fmt::{self, Display};
+
+use crate::{
+    application_manager::{
+        files::{FileTable, PipeId},
+        ipc::ChannelId,
+        signal::SignalState,
+        Application,
+    },
+    interrupt_handlers::TrapFrame,
+    smp::CpuMask,
+};
+
+pub type Pid = usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessState {
+    Ready,
+    Running,
+    Blocked(WaitReason),
+    /// Terminated, but the exit status hasn't been collected yet.
+    Zombie(ExitStatus),
+}
+
+impl Display for ProcessState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProcessState::Ready => write!(f, "ready"),
+            ProcessState::Running => write!(f, "running"),
+            ProcessState::Blocked(_) => write!(f, "blocked"),
+            ProcessState::Zombie(_) => write!(f, "zombie"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitReason {
+    /// Waiting for the termination of another process.
+    Process(Pid),
+    /// Waiting for a message on a channel.
+    Channel(ChannelId),
+    /// Waiting to read from or write to a pipe.
+    Pipe(PipeId),
+    /// Waiting for input typed into the terminal.
+    ConsoleInput,
+    /// Sleeping until the system timer reaches the deadline in microseconds.
+    Sleep(u64),
+}
+
+/// Signals, numbered like on Linux.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(usize)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum Signal {
+    /// Interrupt from the terminal, Ctrl-C.
+    SIGINT = 2,
+    /// Illegal or undefined instruction.
+    SIGILL = 4,
+    /// Breakpoint or single step.
+    SIGTRAP = 5,
+    /// Misaligned PC or stack pointer.
+    SIGBUS = 7,
+    /// Floating-point exception.
+    SIGFPE = 8,
+    /// Terminated by `kill`, can't be handled.
+    SIGKILL = 9,
+    SIGUSR1 = 10,
+    /// Invalid memory access.
+    SIGSEGV = 11,
+    SIGUSR2 = 12,
+    /// Request to terminate.
+    SIGTERM = 15,
+}
+
+impl Signal {
+    pub fn number(self) -> usize {
+        self as usize
+    }
+
+    pub fn from_number(number: usize) -> Option<Self> {
+        Some(match number {
+            2 => Signal::SIGINT,
+            4 => Signal::SIGILL,
+            5 => Signal::SIGTRAP,
+            7 => Signal::SIGBUS,
+            8 => Signal::SIGFPE,
+            9 => Signal::SIGKILL,
+            10 => Signal::SIGUSR1,
+            11 => Signal::SIGSEGV,
+            12 => Signal::SIGUSR2,
+            15 => Signal::SIGTERM,
+            _ => return None,
+        })
+    }
+}
+
+impl Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// Exited via the exit syscall.
+    Exited(i32),
+    /// Terminated by the kernel with a signal.
+    Signaled(Signal),
+}
+
+impl ExitStatus {
+    /// Encodes the status like a POSIX wait status.
+    pub fn wait_status(self) -> usize {
+        match self {
+            ExitStatus::Exited(code) => (code as usize & 0xFF) << 8,
+            ExitStatus::Signaled(signal) => signal.number(),
+        }
+    }
+}
+
+impl Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExitStatus::Exited(code) => write!(f, "exited with code {}", code),
+            ExitStatus::Signaled(signal) => write!(f, "killed by {}", signal),
+        }
+    }
+}
+
+pub struct Process {
+    pub pid: Pid,
+    pub parent: Option<Pid>,
+    pub app_id: usize,
+    pub state: ProcessState,
+    /// Address space of the process, released once it terminates.
+    pub app: Option<Application>,
+    pub files: FileTable,
+    pub signals: SignalState,
+    /// Saved registers, while the process isn't running.
+    pub context: TrapFrame,
+    /// Cores the process may run on.
+    pub affinity: CpuMask,
+    /// Core the process ran on last.
+    pub core: Option<usize>,
+}
+
+impl Process {
+    /// ASID tagging the TLB entries of the process, `0` is left to the kernel.
+    pub fn asid(&self) -> u16 {
+        (self.pid % u16::MAX as Pid) as u16 + 1
+    }
+
+    /// Switches TTBR0 to the address space of the process.
+    pub fn activate(&self) {
+        if let Some(app) = &self.app {
+            app.activate(self.asid());
+        }
+    }
+}
+
+/// Snapshot of a process, as listed by `ps`.
+#[derive(Debug, Clone, Copy)]
+pub struct ProcessInfo {
+    pub pid: Pid,
+    pub parent: Option<Pid>,
+    pub app_id: usize,
+    pub state: ProcessState,
+    /// Core the process is running on.
+    pub core: Option<usize>,
+}
@@ -0,0 +1,77 @@
+//! Run queues of the cores.
+//!
+//! A ready process is queued on the core with the shortest queue among the
+//! cores its affinity allows, staying on its previous core on a tie. A core
+//! running out of work steals from the back of the longest queue it may take
+//! work from, so processes don't wait while another core idles.
+
+use alloc::collections::vec_deque::VecDeque;
+
+use crate::{
+    application_manager::process::Pid,
+    smp::{ready_cores_in, CpuMask, ALL_CORES, CORE_COUNT},
+};
+
+pub(super) struct RunQueues {
+    queues: [VecDeque<Pid>; CORE_COUNT],
+}
+
+impl RunQueues {
+    pub const fn new() -> Self {
+        Self {
+            queues: [const { VecDeque::new() }; CORE_COUNT],
+        }
+    }
+
+    /// Queues `pid` on a running core of `affinity` and returns the core.
+    ///
+    /// A mask without running cores is ignored rather than leaving the
+    /// process stranded.
+    pub fn enqueue(&mut self, pid: Pid, affinity: CpuMask, previous: Option<usize>) -> usize {
+        let core = self
+            .shortest_queue(affinity, previous)
+            .or_else(|| self.shortest_queue(ALL_CORES, previous))
+            .unwrap_or(0);
+        self.queues[core].push_back(pid);
+        core
+    }
+
+    fn shortest_queue(&self, affinity: CpuMask, previous: Option<usize>) -> Option<usize> {
+        ready_cores_in(affinity)
+            .min_by_key(|core| (self.queues[*core].len(), Some(*core) != previous))
+    }
+
+    pub fn pop(&mut self, core: usize) -> Option<Pid> {
+        self.queues[core].pop_front()
+    }
+
+    /// Takes a process queued on another core, which `may_run` allows on `core`.
+    pub fn steal(&mut self, core: usize, may_run: impl Fn(Pid) -> bool) -> Option<Pid> {
+        let mut victims: [usize; CORE_COUNT] = core::array::from_fn(|victim| victim);
+        victims.sort_by_key(|victim| core::cmp::Reverse(self.queues[*victim].len()));
+
+        for victim in victims.into_iter().filter(|victim| *victim != core) {
+            let queue = &mut self.queues[victim];
+            if let Some(index) = queue.iter().rposition(|pid| may_run(*pid)) {
+                return queue.remove(index);
+            }
+        }
+        None
+    }
+
+    /// Whether `core` has queued work or may steal some.
+    pub fn has_work(&self, core: usize, may_run: impl Fn(Pid) -> bool) -> bool {
+        !self.queues[core].is_empty()
+            || self
+                .queues
+                .iter()
+                .enumerate()
+                .any(|(victim, queue)| victim != core && queue.iter().any(|pid| may_run(*pid)))
+    }
+
+    pub fn remove(&mut self, pid: Pid) {
+        for queue in &mut self.queues {
+            queue.retain(|queued| *queued != pid);
+        }
+    }
+}
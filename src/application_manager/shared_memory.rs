@@ -0,0 +1,123 @@
+//! Shared memory objects, mapped into several address spaces at once.
+//!
+//! An object holds a reference to each of its frames and every mapping holds
+//! another one, so the frames are freed once the object has been closed and
+//! its last mapping is gone. Like channels, objects are closed implicitly once
+//! the process which created them terminates.
+
+use alloc::{collections::btree_map::BTreeMap, string::String, vec::Vec};
+use nova_error::NovaError;
+
+use crate::{
+    aarch64::mmu::{allocate_frame, release_frame, PhysAddr, GRANULARITY},
+    application_manager::process::Pid,
+    syscalls::Errno,
+};
+
+pub type SharedMemoryId = usize;
+
+/// Upper bound of the size of a shared memory object, enough for a 1080p framebuffer.
+pub const MAX_SHARED_MEMORY_SIZE: usize = 16 * 1024 * 1024;
+
+pub struct SharedMemory {
+    owner: Pid,
+    name: Option<String>,
+    frames: Vec<PhysAddr>,
+}
+
+impl SharedMemory {
+    /// Allocates zeroed frames for `size_bytes`, rounded up to pages.
+    fn new(owner: Pid, name: Option<String>, size_bytes: usize) -> Result<Self, NovaError> {
+        let mut object = Self {
+            owner,
+            name,
+            frames: Vec::new(),
+        };
+        for _ in 0..size_bytes.div_ceil(GRANULARITY) {
+            // Frames allocated so far are released by dropping the object.
+            object.frames.push(allocate_frame()?);
+        }
+        Ok(object)
+    }
+
+    pub fn frames(&self) -> &[PhysAddr] {
+        &self.frames
+    }
+}
+
+impl Drop for SharedMemory {
+    fn drop(&mut self) {
+        for frame in self.frames.drain(..) {
+            release_frame(frame);
+        }
+    }
+}
+
+pub(super) struct SharedMemoryObjects {
+    objects: BTreeMap<SharedMemoryId, SharedMemory>,
+    next_id: SharedMemoryId,
+}
+
+impl SharedMemoryObjects {
+    pub const fn new() -> Self {
+        Self {
+            objects: BTreeMap::new(),
+            next_id: 1,
+        }
+    }
+
+    /// Creates an object of `size_bytes` owned by `owner`, which can be opened
+    /// by `name` if one is given.
+    pub fn create(
+        &mut self,
+        owner: Pid,
+        name: Option<String>,
+        size_bytes: usize,
+    ) -> Result<SharedMemoryId, Errno> {
+        if size_bytes == 0 || size_bytes > MAX_SHARED_MEMORY_SIZE {
+            return Err(Errno::EINVAL);
+        }
+        if let Some(name) = &name {
+            if self.open(name).is_ok() {
+                return Err(Errno::EEXIST);
+            }
+        }
+
+        let object = SharedMemory::new(owner, name, size_bytes).map_err(|_| Errno::ENOMEM)?;
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.objects.insert(id, object);
+        Ok(id)
+    }
+
+    /// Looks up the id of the object called `name`.
+    pub fn open(&self, name: &str) -> Result<SharedMemoryId, Errno> {
+        self.objects
+            .iter()
+            .find(|(_, object)| object.name.as_deref() == Some(name))
+            .map(|(id, _)| *id)
+            .ok_or(Errno::ENOENT)
+    }
+
+    pub fn get(&self, id: SharedMemoryId) -> Result<&SharedMemory, Errno> {
+        self.objects.get(&id).ok_or(Errno::EBADF)
+    }
+
+    /// Closes the object `id` on behalf of `pid`, existing mappings stay valid.
+    pub fn close(&mut self, id: SharedMemoryId, pid: Pid) -> Result<(), Errno> {
+        match self.objects.get(&id) {
+            None => Err(Errno::EBADF),
+            Some(object) if object.owner != pid => Err(Errno::EPERM),
+            Some(_) => {
+                self.objects.remove(&id);
+                Ok(())
+            }
+        }
+    }
+
+    /// Closes every object owned by `owner`.
+    pub fn close_owned_by(&mut self, owner: Pid) {
+        self.objects.retain(|_, object| object.owner != owner);
+    }
+}
@@ -0,0 +1,123 @@
+//! Signal handling of processes.
+//!
+//! A signal sent to a process stays pending until the process returns to EL0.
+//! Unless a handler has been registered with `sigaction`, the process is then
+//! terminated. For a handler, the interrupted context is saved in a
+//! [`SignalFrame`] on the user stack and the handler is entered with the signal
+//! number in `x0` and the restorer in `x30`. The restorer calls `sigreturn`,
+//! which restores the saved context. A signal is blocked while its handler runs.
+
+use crate::{
+    aarch64::mmu::VirtAddr, application_manager::process::Signal, interrupt_handlers::TrapFrame,
+    syscalls::Errno,
+};
+
+/// Handler address selecting the default action.
+pub const SIG_DFL: VirtAddr = 0;
+
+/// Signal numbers are below this bound.
+const SIGNAL_COUNT: usize = 32;
+
+/// Saved context, which is pushed onto the user stack before a handler is entered.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SignalFrame {
+    pub context: TrapFrame,
+    /// Blocked signals of the interrupted context.
+    pub blocked: u64,
+    pub signal: u64,
+}
+
+/// Pending signals and handlers of a process.
+#[derive(Clone, Default)]
+pub struct SignalState {
+    pending: u64,
+    blocked: u64,
+    handlers: [VirtAddr; SIGNAL_COUNT],
+    /// Code the handlers return to, calling `sigreturn`.
+    restorer: VirtAddr,
+}
+
+impl SignalState {
+    /// State of a forked child, which inherits the handlers but no pending signals.
+    pub fn forked(&self) -> Self {
+        Self {
+            pending: 0,
+            ..self.clone()
+        }
+    }
+
+    /// Resets the handlers, as they belong to the replaced program.
+    pub fn reset_handlers(&mut self) {
+        self.handlers = [SIG_DFL; SIGNAL_COUNT];
+        self.restorer = 0;
+    }
+
+    /// Registers `handler` for `signal` and returns the previous one.
+    pub fn set_handler(
+        &mut self,
+        signal: Signal,
+        handler: VirtAddr,
+        restorer: VirtAddr,
+    ) -> Result<VirtAddr, Errno> {
+        if signal == Signal::SIGKILL || (handler != SIG_DFL && restorer == 0) {
+            return Err(Errno::EINVAL);
+        }
+
+        if handler != SIG_DFL {
+            self.restorer = restorer;
+        }
+        Ok(core::mem::replace(
+            &mut self.handlers[signal.number()],
+            handler,
+        ))
+    }
+
+    pub fn handler(&self, signal: Signal) -> VirtAddr {
+        self.handlers[signal.number()]
+    }
+
+    pub fn restorer(&self) -> VirtAddr {
+        self.restorer
+    }
+
+    pub fn raise(&mut self, signal: Signal) {
+        self.pending |= bit(signal);
+    }
+
+    pub fn is_blocked(&self, signal: Signal) -> bool {
+        self.blocked & bit(signal) != 0
+    }
+
+    /// Whether a pending signal isn't blocked.
+    pub fn has_deliverable(&self) -> bool {
+        self.pending & !self.blocked != 0
+    }
+
+    /// Takes the lowest pending signal, which isn't blocked.
+    pub fn take_pending(&mut self) -> Option<Signal> {
+        let deliverable = self.pending & !self.blocked;
+        if deliverable == 0 {
+            return None;
+        }
+
+        let number = deliverable.trailing_zeros() as usize;
+        self.pending &= !(1 << number);
+        Signal::from_number(number)
+    }
+
+    /// Blocks `signal` and returns the previously blocked signals.
+    pub fn block(&mut self, signal: Signal) -> u64 {
+        let previous = self.blocked;
+        self.blocked |= bit(signal);
+        previous
+    }
+
+    pub fn set_blocked(&mut self, blocked: u64) {
+        self.blocked = blocked & !bit(Signal::SIGKILL);
+    }
+}
+
+fn bit(signal: Signal) -> u64 {
+    1 << signal.number()
+}
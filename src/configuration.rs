@@ -3,7 +3,8 @@ const SCTLR_EL1_DATA_CACHE_DISABLED: u64 = 0 << 2; //C
 const SCTLR_EL1_INSTRUCTION_CACHE_DISABLED: u64 = 0 << 12; //I
 const SCTLR_EL1_LITTLE_ENDIAN_EL0: u64 = 0 << 24; //E0E
 const SCTLR_EL1_LITTLE_ENDIAN_EL1: u64 = 0 << 25; //EE
-const SCTLR_EL1_SPAN: u64 = 1 << 23; //SPAN
+const SCTLR_EL1_SPAN: u64 = 1 << 23; //SPAN, RES1 without PAN
+const SCTLR_EL1_NO_TRAP_WFE: u64 = 1 << 18; //nTWE
 
 #[allow(clippy::identity_op)]
 const SCTLR_EL1_RES: u64 = (0 << 6) | (1 << 11) | (0 << 17) | (1 << 20) | (1 << 22); //Res0 & Res1
@@ -15,7 +16,8 @@ pub static SCTLR_EL1_CONF: u64 = SCTLR_EL1_MMU_ENABLED
     | SCTLR_EL1_LITTLE_ENDIAN_EL0
     | SCTLR_EL1_LITTLE_ENDIAN_EL1
     | SCTLR_EL1_RES
-    | SCTLR_EL1_SPAN;
+    | SCTLR_EL1_SPAN
+    | SCTLR_EL1_NO_TRAP_WFE;
 
 const TG0: u64 = 0b00 << 14; // 4KB granularity EL0
 const T0SZ: u64 = 25; // 25 Bits of TTBR select -> 39 Bits of VA
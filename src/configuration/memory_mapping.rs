@@ -1,27 +1,60 @@
+use core::ops::Range;
+
 use crate::{
-    aarch64::mmu::{
-        alloc_block_l2_explicit, allocate_memory, map_page, physical_mapping::reserve_page,
-        reserve_range, PhysAddr, PhysSource, VirtAddr, DEVICE_MEM, EL0_ACCESSIBLE, GRANULARITY,
-        KERNEL_VIRTUAL_MEM_SPACE, LEVEL1_BLOCK_SIZE, LEVEL2_BLOCK_SIZE, NORMAL_MEM, PXN, READ_ONLY,
-        STACK_START_ADDR, TRANSLATIONTABLE_TTBR0, TRANSLATIONTABLE_TTBR1, UXN, WRITABLE,
+    aarch64::{
+        mmu::{
+            alloc_block_l2_explicit, allocate_memory, map_page, physical_mapping::reserve_page,
+            reserve_range, PhysAddr, PhysSource, VirtAddr, DEVICE_MEM, EL0_ACCESSIBLE, GRANULARITY,
+            KERNEL_VIRTUAL_MEM_SPACE, LEVEL1_BLOCK_SIZE, LEVEL2_BLOCK_SIZE, NORMAL_MEM, PXN,
+            READ_ONLY, STACK_START_ADDR, TRANSLATIONTABLE_TTBR0, TRANSLATIONTABLE_TTBR1, UXN,
+            WRITABLE,
+        },
+        registers::current_core,
     },
+    pi3::local_interrupt_controller::LOCAL_PERIPHERAL_BASE,
+    smp::CORE_COUNT,
     PERIPHERAL_BASE,
 };
 
+/// Top of the EL1 stack of core 0, the stacks of the other cores follow below.
 #[no_mangle]
 static EL1_STACK_TOP: usize = STACK_START_ADDR | KERNEL_VIRTUAL_MEM_SPACE;
 const EL1_STACK_SIZE: usize = LEVEL2_BLOCK_SIZE * 2;
+/// Distance between the EL1 stacks, leaving an unmapped block between them.
+#[no_mangle]
+static EL1_STACK_STRIDE: usize = EL1_STACK_SIZE + LEVEL2_BLOCK_SIZE;
 #[no_mangle]
 pub static EL0_STACK_TOP: usize = STACK_START_ADDR;
 pub const EL0_STACK_SIZE: usize = LEVEL2_BLOCK_SIZE * 2;
+/// Lowest address of an application stack, mapped separately in every application.
+pub const EL0_STACK_BOTTOM: VirtAddr = STACK_START_ADDR + 0x10 - EL0_STACK_SIZE;
+/// Unmapped page below an application stack.
+pub const EL0_STACK_GUARD_PAGE: VirtAddr = EL0_STACK_BOTTOM - GRANULARITY;
+
+/// Start of the program break, grown by `brk`.
+pub const USER_HEAP_BASE: VirtAddr = 0x10_0000_0000;
+/// Upper bound of the program break.
+pub const USER_HEAP_END: VirtAddr = USER_HEAP_BASE + LEVEL1_BLOCK_SIZE;
+/// Range anonymous mappings created by `mmap` are placed in.
+pub const USER_MMAP_BASE: VirtAddr = 0x20_0000_0000;
+pub const USER_MMAP_END: VirtAddr = 0x40_0000_0000;
+
+/// Initial stack pointer of the EL1 stack of `core`.
+pub fn el1_stack_top(core: usize) -> VirtAddr {
+    EL1_STACK_TOP - core * EL1_STACK_STRIDE
+}
+
+/// Addresses occupied by the EL1 stack of the calling core.
+pub fn el1_stack_range() -> Range<VirtAddr> {
+    let top = el1_stack_top(current_core());
+    top + 0x10 - EL1_STACK_SIZE..top + 0x10
+}
 
 pub const MAILBOX_VIRTUAL_ADDRESS: VirtAddr = 0xFFFF_FF81_FFFF_E000;
+/// Writable mapping of the first page, which holds the spin table of the parked cores.
+pub const SPIN_TABLE_VIRTUAL_ADDRESS: VirtAddr = 0xFFFF_FF81_FFFF_D000;
 pub static mut MAILBOX_PHYSICAL_ADDRESS: Option<PhysAddr> = None;
 
-// TODO: Currently limited to 512 applications, more than enough, but has to be kept
-// in mind
-pub const APPLICATION_TRANSLATION_TABLE_VA: VirtAddr = 0xFFFF_FF81_FE00_0000;
-
 extern "C" {
     static __text_end: u64;
     static __share_end: u64;
@@ -85,6 +118,15 @@ pub fn initialize_mmu_translation_tables() {
         .unwrap();
     }
 
+    // Core-local interrupt controller and mailboxes, only accessed by the kernel
+    alloc_block_l2_explicit(
+        LOCAL_PERIPHERAL_BASE as usize,
+        LOCAL_PERIPHERAL_BASE as usize,
+        core::ptr::addr_of_mut!(TRANSLATIONTABLE_TTBR0),
+        WRITABLE | UXN | PXN | DEVICE_MEM,
+    )
+    .unwrap();
+
     // Frame Buffer memory range
     allocate_memory(
         0x3c100000,
@@ -94,21 +136,23 @@ pub fn initialize_mmu_translation_tables() {
     )
     .unwrap();
 
-    // Allocate EL1 stack
-    allocate_memory(
-        EL1_STACK_TOP - EL1_STACK_SIZE + 0x10,
-        EL1_STACK_SIZE,
-        PhysSource::Any,
-        WRITABLE | NORMAL_MEM,
-    )
-    .unwrap();
+    // Allocate the EL1 stacks, they're backed eagerly as faults on them can't be
+    // handled on the stack itself.
+    for core in 0..CORE_COUNT {
+        allocate_memory(
+            el1_stack_top(core) - EL1_STACK_SIZE + 0x10,
+            EL1_STACK_SIZE,
+            PhysSource::Any,
+            WRITABLE | NORMAL_MEM,
+        )
+        .unwrap();
+    }
 
-    // Allocate EL0 stack
     allocate_memory(
-        EL0_STACK_TOP - EL0_STACK_SIZE + 0x10,
-        EL0_STACK_SIZE,
-        PhysSource::Any,
-        WRITABLE | EL0_ACCESSIBLE | NORMAL_MEM,
+        SPIN_TABLE_VIRTUAL_ADDRESS,
+        GRANULARITY,
+        PhysSource::Explicit(0),
+        WRITABLE | NORMAL_MEM,
     )
     .unwrap();
 
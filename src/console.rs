@@ -1,17 +1,34 @@
-use alloc::string::String;
+use alloc::{format, string::String, vec, vec::Vec};
 
 use crate::{
-    application_manager::start_app,
+    application_manager::{
+        kill,
+        process::{Pid, Signal},
+        processes, push_console_input, send_signal, start_app, start_pipeline,
+    },
+    cpufreq::{self, Governor},
+    idle::{idle_time, IdleSample},
     interrupt_handlers::irq::{register_interrupt_handler, IRQSource},
     peripherals::uart::read_uart_data,
     pi3::mailbox::read_soc_temp,
     print, println,
+    smp::CORE_COUNT,
+    sync::IrqSpinLock,
+    time::{set_wall_clock, uptime, DateTime, Duration},
 };
 
-pub static mut TERMINAL: Option<Terminal> = None;
+pub static TERMINAL: IrqSpinLock<Option<Terminal>> = IrqSpinLock::new(None);
+
+/// Idle times at the last `cpu` command, which reports the load since then.
+static CPU_SAMPLE: IrqSpinLock<IdleSample> = IrqSpinLock::new(IdleSample::BOOT);
+
+/// Input byte of Ctrl-C.
+const CTRL_C: char = '\x03';
 
 pub struct Terminal {
     input: String,
+    /// Processes started by the last command, which receive SIGINT on Ctrl-C.
+    foreground: Vec<Pid>,
 }
 
 impl Default for Terminal {
@@ -24,6 +41,7 @@ impl Terminal {
     pub fn new() -> Self {
         Self {
             input: String::new(),
+            foreground: Vec::new(),
         }
     }
 
@@ -36,6 +54,11 @@ impl Terminal {
         let val = self.input.clone();
         self.input.clear();
 
+        if val.contains('|') {
+            self.exec_pipeline(&val);
+            return;
+        }
+
         let mut parts = val.split(" ");
 
         match parts.next().unwrap() {
@@ -45,28 +68,198 @@ impl Terminal {
             "app" => {
                 if let Some(app_id) = parts.next().and_then(|a| a.parse::<usize>().ok()) {
                     let args = parts.collect();
-                    let _ = start_app(app_id, args);
+                    if let Ok(pid) = start_app(app_id, args) {
+                        println!("Started app {} as process {}", app_id, pid);
+                        self.foreground = vec![pid];
+                    }
                 } else {
                     println!("App ID not set.");
                 }
             }
+            "cpu" => {
+                let now = IdleSample::take();
+                let earlier = core::mem::replace(&mut *CPU_SAMPLE.lock(), now);
+                println!(
+                    "Load over {:.1}s:",
+                    now.duration_since(&earlier).as_secs_f32()
+                );
+                println!("CORE  LOAD  IDLE");
+                for core in 0..CORE_COUNT {
+                    match now.load_since(&earlier, core) {
+                        Some(load) => {
+                            let idle = idle_time(core).as_secs();
+                            println!("{:<5} {:>3}%  {}s", core, load, idle);
+                        }
+                        None => {
+                            println!("{:<5} offline", core);
+                        }
+                    }
+                }
+            }
+            "cpufreq" => match (parts.next(), parts.next()) {
+                (None, _) => print_cpufreq(),
+                (Some("governor"), Some(name)) => match Governor::from_name(name) {
+                    Some(governor) => {
+                        if let Err(err) = cpufreq::set_governor(governor) {
+                            println!("Unable to set governor: {:?}", err);
+                        }
+                    }
+                    None => {
+                        println!("Governors: performance, powersave, ondemand, userspace");
+                    }
+                },
+                (Some("set"), Some(mhz)) => match mhz.parse::<u32>() {
+                    Ok(mhz) => match cpufreq::set_rate(mhz.saturating_mul(1_000_000)) {
+                        Ok(hz) => {
+                            println!("ARM clock set to {} MHz", hz / 1_000_000);
+                        }
+                        Err(err) => {
+                            println!("Unable to set clock: {:?}", err);
+                        }
+                    },
+                    Err(_) => {
+                        println!("Usage: cpufreq set <MHz>");
+                    }
+                },
+                _ => {
+                    println!("Usage: cpufreq [governor <name> | set <MHz>]");
+                }
+            },
+            "uptime" => {
+                let seconds = uptime().as_secs();
+                println!(
+                    "up {}:{:02}:{:02}",
+                    seconds / 3600,
+                    seconds / 60 % 60,
+                    seconds % 60
+                );
+            }
+            "date" => match parts.next().map(|a| a.parse::<u64>()) {
+                None => {
+                    println!("{} UTC", DateTime::now());
+                }
+                Some(Ok(seconds)) => set_wall_clock(Duration::from_secs(seconds)),
+                Some(Err(_)) => {
+                    println!("Usage: date [seconds since the Unix epoch]");
+                }
+            },
+            "ps" => {
+                println!("PID   PPID  APP   CORE  STATE");
+                for process in processes() {
+                    let parent = process
+                        .parent
+                        .map_or(String::from("-"), |parent| format!("{}", parent));
+                    let core = process
+                        .core
+                        .map_or(String::from("-"), |core| format!("{}", core));
+                    println!(
+                        "{:<5} {:<5} {:<5} {:<5} {}",
+                        process.pid, parent, process.app_id, core, process.state
+                    );
+                }
+            }
+            "kill" => {
+                if let Some(pid) = parts.next().and_then(|a| a.parse::<usize>().ok()) {
+                    if let Err(err) = kill(pid) {
+                        println!("Unable to kill process {}: {:?}", pid, err);
+                    }
+                } else {
+                    println!("PID not set.");
+                }
+            }
             _ => {
                 println!("Unknown command: \"{}\"", self.input);
             }
         }
         self.input.clear();
     }
+
+    /// Runs commands like `app 0 | app 1 5`, connecting them by pipes.
+    fn exec_pipeline(&mut self, line: &str) {
+        let mut commands = Vec::new();
+        for command in line.split('|') {
+            let mut parts = command.split_whitespace();
+            if parts.next() != Some("app") {
+                println!("Only apps can be part of a pipeline.");
+                return;
+            }
+            let Some(app_id) = parts.next().and_then(|a| a.parse::<usize>().ok()) else {
+                println!("App ID not set.");
+                return;
+            };
+            commands.push((app_id, parts.collect()));
+        }
+
+        if let Ok(pids) = start_pipeline(commands) {
+            println!("Started pipeline as processes {:?}", pids);
+            self.foreground = pids;
+        }
+    }
+
+    /// Interrupts the processes started by the last command.
+    fn interrupt(&mut self) {
+        print!("^C");
+        for pid in self.foreground.drain(..) {
+            // Processes which already terminated are skipped.
+            let _ = send_signal(pid, Signal::SIGINT);
+        }
+        self.input.clear();
+        self.flush();
+    }
+}
+
+/// Prints the governor, rate and voltage of the ARM clock.
+fn print_cpufreq() {
+    match (cpufreq::governor(), cpufreq::rate(), cpufreq::limits()) {
+        (Ok(governor), Ok(hz), Ok((min_hz, max_hz))) => {
+            println!("governor: {}", governor);
+            println!(
+                "rate: {} MHz ({} to {} MHz)",
+                hz / 1_000_000,
+                min_hz / 1_000_000,
+                max_hz / 1_000_000
+            );
+        }
+        _ => {
+            println!("Clock scaling unavailable.");
+            return;
+        }
+    }
+    if let Ok(microvolts) = cpufreq::voltage() {
+        println!(
+            "voltage: {}.{:04} V",
+            microvolts / 1_000_000,
+            microvolts % 1_000_000 / 100
+        );
+    }
 }
 
 pub fn init_terminal() {
-    unsafe { TERMINAL = Some(Terminal::new()) };
+    *TERMINAL.lock() = Some(Terminal::new());
     register_terminal_interrupt_handler();
 }
 
 fn terminal_uart_rx_interrupt_handler() {
     let input = read_uart_data();
-    #[allow(static_mut_refs)]
-    if let Some(term) = unsafe { TERMINAL.as_mut() } {
+
+    if input == CTRL_C {
+        if let Some(term) = TERMINAL.lock().as_mut() {
+            term.interrupt();
+        }
+        return;
+    }
+
+    // Processes reading the console take precedence over the terminal.
+    let byte = if input == '\r' { b'\n' } else { input as u8 };
+    if push_console_input(byte) {
+        match input {
+            '\r' => print!("\r\n"),
+            _ => print!("{}", input),
+        }
+        return;
+    }
+
+    if let Some(term) = TERMINAL.lock().as_mut() {
         match input {
             '\r' => {
                 term.exec();
@@ -80,10 +273,13 @@ fn terminal_uart_rx_interrupt_handler() {
     }
 }
 
+/// Prints the prompt again, unless the terminal is busy running a command,
+/// which prints it once it is done.
 pub fn flush_terminal() {
-    #[allow(static_mut_refs)]
-    if let Some(term) = unsafe { TERMINAL.as_mut() } {
-        term.flush();
+    if let Some(mut terminal) = TERMINAL.try_lock() {
+        if let Some(term) = terminal.as_mut() {
+            term.flush();
+        }
     }
 }
 
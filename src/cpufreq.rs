@@ -0,0 +1,217 @@
+//! Scaling of the ARM clock, which all cores share.
+//!
+//! The firmware switches the clock through the mailbox and adjusts the core
+//! voltage along with it. The rate moves between levels [`LEVEL_STEP_HZ`]
+//! apart, picked by a [`Governor`].
+
+use core::fmt::{self, Display};
+
+use log::info;
+use nova_error::NovaError;
+
+use crate::{
+    idle::IdleSample,
+    pi3::mailbox::{
+        get_clock_rate, get_max_clock_rate, get_min_clock_rate, get_voltage, set_clock_rate,
+        CLOCK_ARM, VOLTAGE_CORE,
+    },
+    smp::CORE_COUNT,
+    sync::IrqSpinLock,
+    timer::{self, TimerId},
+};
+
+/// Distance between two clock levels.
+const LEVEL_STEP_HZ: u32 = 100_000_000;
+
+/// Period in which `ondemand` measures the load.
+const SAMPLING_PERIOD_US: u64 = 100_000;
+
+/// Load in percent above which `ondemand` switches to the maximum rate.
+const UP_THRESHOLD: u64 = 80;
+
+const DEFAULT_GOVERNOR: Governor = Governor::Ondemand;
+
+/// Policy choosing the clock rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Governor {
+    /// Always the maximum rate.
+    Performance,
+    /// Always the minimum rate.
+    Powersave,
+    /// The lowest rate keeping the busiest core below [`UP_THRESHOLD`].
+    Ondemand,
+    /// The rate last set by [`set_rate`].
+    Userspace,
+}
+
+impl Governor {
+    pub const ALL: [Governor; 4] = [
+        Governor::Performance,
+        Governor::Powersave,
+        Governor::Ondemand,
+        Governor::Userspace,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Governor::Performance => "performance",
+            Governor::Powersave => "powersave",
+            Governor::Ondemand => "ondemand",
+            Governor::Userspace => "userspace",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|governor| governor.name() == name)
+    }
+}
+
+impl Display for Governor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+struct CpuFreq {
+    governor: Governor,
+    min_hz: u32,
+    max_hz: u32,
+    /// Rate last set, the firmware may have reduced it on overheating.
+    rate_hz: u32,
+    /// Idle statistics at the last sampling of `ondemand`.
+    sample: IdleSample,
+    /// Sampling timer of `ondemand`.
+    timer: Option<TimerId>,
+}
+
+impl CpuFreq {
+    /// Rounds `hz` up to a level within the limits.
+    fn level(&self, hz: u32) -> u32 {
+        let steps = hz.saturating_sub(self.min_hz).div_ceil(LEVEL_STEP_HZ);
+        self.min_hz
+            .saturating_add(steps.saturating_mul(LEVEL_STEP_HZ))
+            .min(self.max_hz)
+    }
+
+    fn apply(&mut self, hz: u32) -> Result<(), NovaError> {
+        let hz = self.level(hz);
+        if hz != self.rate_hz {
+            // Turbo settings stay up to the firmware.
+            self.rate_hz = set_clock_rate([CLOCK_ARM, hz, 0])?[1];
+        }
+        Ok(())
+    }
+
+    fn set_governor(&mut self, governor: Governor) -> Result<(), NovaError> {
+        if let Some(id) = self.timer.take() {
+            timer::cancel(id);
+        }
+        self.governor = governor;
+
+        match governor {
+            Governor::Performance => self.apply(self.max_hz),
+            Governor::Powersave => self.apply(self.min_hz),
+            Governor::Ondemand => {
+                self.sample = IdleSample::take();
+                self.timer = Some(timer::schedule_periodic(SAMPLING_PERIOD_US, sample_load));
+                Ok(())
+            }
+            Governor::Userspace => Ok(()),
+        }
+    }
+
+    /// Scales the rate with the load of the busiest core since the last sample.
+    fn sample_load(&mut self) -> Result<(), NovaError> {
+        let now = IdleSample::take();
+        let load = (0..CORE_COUNT)
+            .filter_map(|core| now.load_since(&self.sample, core))
+            .max()
+            .unwrap_or(0);
+        self.sample = now;
+
+        let target = if load > UP_THRESHOLD {
+            self.max_hz
+        } else {
+            (self.max_hz as u64 * load / UP_THRESHOLD) as u32
+        };
+        self.apply(target)
+    }
+}
+
+static CPUFREQ: IrqSpinLock<Option<CpuFreq>> = IrqSpinLock::new(None);
+
+/// Queries the limits of the ARM clock and starts the default governor.
+pub fn initialize_cpufreq() -> Result<(), NovaError> {
+    let min_hz = get_min_clock_rate([CLOCK_ARM])?[1];
+    let max_hz = get_max_clock_rate([CLOCK_ARM])?[1];
+    let rate_hz = get_clock_rate([CLOCK_ARM])?[1];
+    info!(
+        "ARM clock at {} MHz, {} to {} MHz",
+        rate_hz / 1_000_000,
+        min_hz / 1_000_000,
+        max_hz / 1_000_000
+    );
+
+    let mut cpufreq = CPUFREQ.lock();
+    let state = cpufreq.insert(CpuFreq {
+        governor: Governor::Userspace,
+        min_hz,
+        max_hz,
+        rate_hz,
+        sample: IdleSample::take(),
+        timer: None,
+    });
+    state.set_governor(DEFAULT_GOVERNOR)
+}
+
+fn with_cpufreq<R>(f: impl FnOnce(&mut CpuFreq) -> Result<R, NovaError>) -> Result<R, NovaError> {
+    f(CPUFREQ
+        .lock()
+        .as_mut()
+        .ok_or(NovaError::General("Cpufreq not initialized."))?)
+}
+
+pub fn governor() -> Result<Governor, NovaError> {
+    with_cpufreq(|state| Ok(state.governor))
+}
+
+/// Switches to `governor`, which sets its rate right away.
+pub fn set_governor(governor: Governor) -> Result<(), NovaError> {
+    with_cpufreq(|state| state.set_governor(governor))
+}
+
+/// Lowest and highest rate of the ARM clock in Hz.
+pub fn limits() -> Result<(u32, u32), NovaError> {
+    with_cpufreq(|state| Ok((state.min_hz, state.max_hz)))
+}
+
+/// Current rate of the ARM clock in Hz, as reported by the firmware.
+pub fn rate() -> Result<u32, NovaError> {
+    Ok(get_clock_rate([CLOCK_ARM])?[1])
+}
+
+/// Pins the ARM clock to the level at or above `hz` and returns the rate set.
+///
+/// Switches to the `userspace` governor, so the rate stays.
+pub fn set_rate(hz: u32) -> Result<u32, NovaError> {
+    with_cpufreq(|state| {
+        state.set_governor(Governor::Userspace)?;
+        state.apply(hz)?;
+        Ok(state.rate_hz)
+    })
+}
+
+/// Current voltage of the cores in µV.
+pub fn voltage() -> Result<u32, NovaError> {
+    Ok(get_voltage([VOLTAGE_CORE])?[1])
+}
+
+/// Sampling timer of the `ondemand` governor.
+fn sample_load() {
+    let _ = with_cpufreq(|state| match state.governor {
+        Governor::Ondemand => state.sample_load(),
+        _ => Ok(()),
+    });
+}
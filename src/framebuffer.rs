@@ -4,7 +4,7 @@ mod bitmaps;
 
 use bitmaps::BASIC_LEGACY;
 
-use crate::pi3::mailbox::{read_mailbox, write_mailbox};
+use crate::pi3::mailbox::call_mailbox;
 use log::error;
 #[repr(align(16))]
 struct Mailbox([u32; 36]);
@@ -26,6 +26,9 @@ pub struct FrameBuffer {
     pub size: u32, //Bytes
 }
 
+// The frame buffer is owned by whoever owns this handle.
+unsafe impl Send for FrameBuffer {}
+
 pub const RED: u32 = 0x00FF0000;
 pub const GREEN: u32 = 0x0000FF00;
 pub const BLUE: u32 = 0x000000FF;
@@ -231,9 +234,7 @@ impl Default for FrameBuffer {
 
         let addr = core::ptr::addr_of!(mailbox.0[0]) as u32;
 
-        write_mailbox(8, addr);
-
-        let _ = read_mailbox(8);
+        let _ = call_mailbox(8, addr);
         if mailbox.0[1] == 0 {
             error!("Mailbox request was not processed!");
         }
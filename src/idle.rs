@@ -0,0 +1,153 @@
+//! Idling of the cores.
+//!
+//! A core without runnable work sleeps in `wfi` until an interrupt arrives,
+//! and waits for locks and devices in `wfe`. Releasing a lock sends an event,
+//! for everything else the event stream of the generic timer wakes `wfe`
+//! periodically, so a wait checks its condition at least [`EVENT_STREAM_HZ`]
+//! times per second.
+//!
+//! The time each core spends in `wfi` is accounted, to report how busy the
+//! cores are.
+
+use core::{
+    arch::asm,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::{
+    aarch64::generic_timer,
+    smp::{is_core_ready, CORE_COUNT},
+    sync::PerCpu,
+    time::{Duration, Instant},
+};
+
+/// Rate of the `wfe` wakeups of the event stream.
+const EVENT_STREAM_HZ: u64 = 10_000;
+
+/// Marks a core which isn't idle in [`IDLE_SINCE`].
+const NOT_IDLE: u64 = u64::MAX;
+
+/// Microseconds each core has spent idle, excluding the current idle period.
+static IDLE_TIME: PerCpu<AtomicU64> = PerCpu::new([const { AtomicU64::new(0) }; CORE_COUNT]);
+/// Start of the current idle period of each core, in microseconds since boot.
+static IDLE_SINCE: PerCpu<AtomicU64> =
+    PerCpu::new([const { AtomicU64::new(NOT_IDLE) }; CORE_COUNT]);
+
+/// Prepares the calling core for waiting on events.
+pub fn initialize_idle() {
+    generic_timer::enable_event_stream(EVENT_STREAM_HZ);
+}
+
+/// Lets waits for events end in EL2, before [`initialize_idle`] in EL1.
+pub fn initialize_idle_el2() {
+    generic_timer::enable_event_stream_el2(EVENT_STREAM_HZ);
+}
+
+/// Sleeps until an interrupt is pending and accounts the time as idle.
+///
+/// IRQs should be masked, after checking there is no work, so an interrupt
+/// can't slip in before the `wfi`. It still ends the wait, its handler runs
+/// once IRQs are unmasked again.
+pub fn idle() {
+    let start = Instant::now().as_micros();
+    IDLE_SINCE.get().store(start, Ordering::Relaxed);
+    wait_for_interrupt();
+    IDLE_SINCE.get().store(NOT_IDLE, Ordering::Relaxed);
+
+    let end = Instant::now().as_micros();
+    IDLE_TIME
+        .get()
+        .fetch_add(end.saturating_sub(start), Ordering::Relaxed);
+}
+
+/// Sleeps until an interrupt is pending, even if interrupts are masked.
+pub fn wait_for_interrupt() {
+    unsafe { asm!("dsb sy", "wfi") };
+}
+
+/// Sleeps until an event, at the latest until the next one of the event stream.
+#[inline(always)]
+pub fn wait_for_event() {
+    unsafe { asm!("wfe", options(nomem, nostack)) };
+}
+
+/// Wakes the cores waiting for an event, after the preceding stores are visible.
+#[inline(always)]
+pub fn send_event() {
+    unsafe { asm!("dsb ish", "sev", options(nostack)) };
+}
+
+/// Waits in `wfe` until `done` holds, like for a status register of a device.
+pub fn wait_until(mut done: impl FnMut() -> bool) {
+    while !done() {
+        wait_for_event();
+    }
+}
+
+/// Waits in `wfe` for `duration`, also with IRQs masked.
+pub fn sleep(duration: Duration) {
+    let deadline = Instant::after(duration);
+    wait_until(|| deadline.has_passed());
+}
+
+/// Stops the calling core for good, it only serves interrupts anymore.
+pub fn halt() -> ! {
+    loop {
+        wait_for_interrupt();
+    }
+}
+
+/// Time `core` has spent idle since boot.
+pub fn idle_time(core: usize) -> Duration {
+    let mut micros = IDLE_TIME.for_core(core).load(Ordering::Relaxed);
+    let since = IDLE_SINCE.for_core(core).load(Ordering::Relaxed);
+    if since != NOT_IDLE {
+        micros += Instant::now().as_micros().saturating_sub(since);
+    }
+    Duration::from_micros(micros)
+}
+
+/// Idle times of all cores at one instant, to measure their load in between.
+#[derive(Debug, Clone, Copy)]
+pub struct IdleSample {
+    at: Instant,
+    idle: [Duration; CORE_COUNT],
+}
+
+impl IdleSample {
+    /// Sample at boot, when no core has been idle yet.
+    pub const BOOT: Self = Self {
+        at: Instant::BOOT,
+        idle: [Duration::ZERO; CORE_COUNT],
+    };
+
+    pub fn take() -> Self {
+        Self {
+            at: Instant::now(),
+            idle: core::array::from_fn(idle_time),
+        }
+    }
+
+    /// Time between `earlier` and this sample.
+    pub fn duration_since(&self, earlier: &Self) -> Duration {
+        self.at.duration_since(earlier.at)
+    }
+
+    /// Share of the time between `earlier` and this sample, `core` has been
+    /// busy, in percent.
+    ///
+    /// Returns `None` for a core which doesn't run the kernel.
+    pub fn load_since(&self, earlier: &Self, core: usize) -> Option<u64> {
+        if !is_core_ready(core) {
+            return None;
+        }
+        let elapsed = self.duration_since(earlier).as_micros() as u64;
+        if elapsed == 0 {
+            return Some(0);
+        }
+        let idle = self.idle[core]
+            .saturating_sub(earlier.idle[core])
+            .as_micros() as u64;
+        Some(elapsed.saturating_sub(idle) * 100 / elapsed)
+    }
+}
@@ -1,19 +1,38 @@
-use core::arch::asm;
-
 use crate::{
-    aarch64::registers::{daif::mask_all, read_esr_el1, read_exception_source_el},
-    get_current_el,
+    aarch64::{
+        backtrace::log_backtrace,
+        registers::{daif::mask_all, read_esr_el1, read_far_el1},
+    },
+    application_manager::{
+        exit_current,
+        process::{ExitStatus, Signal},
+        schedule,
+    },
+    syscalls::user_access::search_exception_table,
 };
-use log::debug;
+use alloc::{format, string::String};
+use log::error;
 
 const INTERRUPT_BASE: u32 = 0x3F00_B000;
 const IRQ_PENDING_BASE: u32 = INTERRUPT_BASE + 0x204;
 const ENABLE_IRQ_BASE: u32 = INTERRUPT_BASE + 0x210;
 const DISABLE_IRQ_BASE: u32 = INTERRUPT_BASE + 0x21C;
+const FIQ_CONTROL: u32 = INTERRUPT_BASE + 0x20C;
 
 const GPIO_PENDING_BIT_OFFSET: u64 = 0b1111 << 49;
 
+/// SPSR value for returning into EL1h with all interrupts unmasked.
+const SPSR_EL1H: u64 = 0b0101;
+
+extern "C" {
+    fn kernel_loop_trampoline();
+}
+
+/// Register state of the interrupted context, saved by `save_context` in `vector.S`.
+///
+/// Modifying the frame changes the context the exception returns to.
 #[repr(C)]
+#[derive(Clone, Copy, Default)]
 pub struct TrapFrame {
     pub x0: u64,
     pub x1: u64,
@@ -34,8 +53,49 @@ pub struct TrapFrame {
     pub x16: u64,
     pub x17: u64,
     pub x18: u64,
+    pub x19: u64,
+    pub x20: u64,
+    pub x21: u64,
+    pub x22: u64,
+    pub x23: u64,
+    pub x24: u64,
+    pub x25: u64,
+    pub x26: u64,
+    pub x27: u64,
+    pub x28: u64,
     pub x29: u64,
     pub x30: u64,
+    pub sp_el0: u64,
+    pub elr: u64,
+    pub spsr: u64,
+}
+
+impl TrapFrame {
+    /// Whether the exception was taken from EL0.
+    pub fn from_el0(&self) -> bool {
+        self.spsr & 0b1111 == 0
+    }
+
+    /// Registers `x0` to `x30`.
+    fn general_purpose_registers(&self) -> &[u64; 31] {
+        unsafe { &*(self as *const Self as *const [u64; 31]) }
+    }
+
+    /// Logs all registers of the frame.
+    pub fn log_registers(&self) {
+        for (row, values) in self.general_purpose_registers().chunks(4).enumerate() {
+            let line: String = values
+                .iter()
+                .enumerate()
+                .map(|(column, value)| format!("x{:<2} {:#018x}  ", row * 4 + column, value))
+                .collect();
+            error!("{}", line.trim_end());
+        }
+        error!(
+            "sp_el0 {:#018x}  elr {:#018x}  spsr {:#010x}",
+            self.sp_el0, self.elr, self.spsr
+        );
+    }
 }
 
 /// Representation of the ESR_ELx registers
@@ -49,6 +109,57 @@ struct EsrElX {
     iss: u32,
 }
 
+impl EsrElX {
+    /// Describes the exception class.
+    fn class_description(&self) -> &'static str {
+        match self.ec {
+            0b000000 => "Unknown reason",
+            0b000001 => "Trapped WFI or WFE instruction",
+            0b000011 => "Trapped MCR or MRC access (coproc 0b1111)",
+            0b000100 => "Trapped MCRR or MRRC access (coproc 0b1111)",
+            0b000101 => "Trapped MCR or MRC access (coproc 0b1110)",
+            0b000110 => "Trapped LDC or STC access",
+            0b000111 => "Trapped access to SVE, Advanced SIMD or floating-point",
+            0b001100 => "Trapped MRRC access (coproc 0b1110)",
+            0b001101 => "Branch Target Exception",
+            0b001110 => "Illegal Execution state",
+            0b010001 => "SVC instruction execution in AArch32",
+            0b010101 => "SVC instruction execution in AArch64",
+            0b010110 => "HVC instruction execution in AArch64",
+            0b010111 => "SMC instruction execution in AArch64",
+            0b011000 => "Trapped MSR, MRS or System instruction",
+            0b011001 => "Trapped access to SVE",
+            0b011100 => "Pointer authentication failure",
+            0b100000 => "Instruction Abort from a lower Exception level",
+            0b100001 => "Instruction Abort without a change in Exception level",
+            0b100010 => "PC alignment fault",
+            0b100100 => "Data Abort from a lower Exception level",
+            0b100101 => "Data Abort without a change in Exception level",
+            0b100110 => "SP alignment fault",
+            0b101000 => "Trapped floating-point exception in AArch32",
+            0b101100 => "Trapped floating-point exception in AArch64",
+            0b101111 => "SError interrupt",
+            0b110000 => "Breakpoint from a lower Exception level",
+            0b110001 => "Breakpoint without a change in Exception level",
+            0b110010 => "Software Step from a lower Exception level",
+            0b110011 => "Software Step without a change in Exception level",
+            0b110100 => "Watchpoint from a lower Exception level",
+            0b110101 => "Watchpoint without a change in Exception level",
+            0b111000 => "BKPT instruction execution in AArch32",
+            0b111100 => "BRK instruction execution in AArch64",
+            _ => "Reserved",
+        }
+    }
+
+    /// Whether FAR_EL1 holds the faulting address for this exception class.
+    fn has_fault_address(&self) -> bool {
+        matches!(
+            self.ec,
+            0b100000 | 0b100001 | 0b100010 | 0b100100 | 0b100101 | 0b110100 | 0b110101
+        )
+    }
+}
+
 impl From<u32> for EsrElX {
     fn from(value: u32) -> Self {
         Self {
@@ -63,21 +174,75 @@ pub mod irq;
 pub mod synchronous;
 
 #[no_mangle]
-unsafe extern "C" fn rust_synchronous_interrupt_no_el_change() {
+unsafe extern "C" fn rust_synchronous_interrupt_no_el_change(frame: &mut TrapFrame) {
     mask_all();
 
-    let source_el = read_exception_source_el() >> 2;
-    debug!("--------Sync Exception in EL{}--------", source_el);
-    debug!("No EL change");
-    debug!("Current EL: {}", get_current_el());
-    debug!("{:?}", EsrElX::from(read_esr_el1()));
-    debug!("Return register address: {:#x}", read_esr_el1());
-    debug!("-------------------------------------");
+    let esr = EsrElX::from(read_esr_el1());
+    match esr.ec {
+        // Data abort taken without a change in EL
+        0b100101 => {
+            if let Some(fixup) = search_exception_table(frame.elr as usize) {
+                frame.elr = fixup as u64;
+                return;
+            }
+        }
+        // SVC from the kernel loop, handing the CPU to a ready process
+        0b010101 => {
+            schedule(frame);
+            return;
+        }
+        _ => {}
+    }
+
+    kernel_oops(frame, esr);
 }
 
-fn set_return_to_kernel_loop() {
-    unsafe {
-        asm!("ldr x0, =kernel_loop", "msr ELR_EL1, x0");
-        asm!("mov x0, #(0b0101)", "msr SPSR_EL1, x0");
+/// SError Handler
+///
+/// Asynchronous aborts from EL0 terminate the current process, in EL1 they are fatal.
+#[no_mangle]
+unsafe extern "C" fn rust_serror_handler(frame: &mut TrapFrame) {
+    mask_all();
+
+    let esr = EsrElX::from(read_esr_el1());
+    if frame.from_el0() {
+        synchronous::log_crash_report(frame, esr, 0);
+        exit_current(ExitStatus::Signaled(Signal::SIGBUS));
+        schedule(frame);
+        return;
     }
+
+    kernel_oops(frame, esr);
+}
+
+/// Reports an exception the kernel can't recover from and panics.
+fn kernel_oops(frame: &TrapFrame, esr: EsrElX) -> ! {
+    error!("-------- Kernel oops --------");
+    error!("Exception: {}", esr.class_description());
+    if matches!(esr.ec, 0b100001 | 0b100101) {
+        error!(
+            "Cause: {}",
+            synchronous::decode_data_abort(esr.iss as usize)
+        );
+    }
+    error!("ESR: {:#010x}", read_esr_el1());
+    error!("ELR: {:#018x}", frame.elr);
+    if esr.has_fault_address() {
+        error!("FAR: {:#018x}", read_far_el1());
+    }
+    frame.log_registers();
+    error!(
+        "sp_el1 {:#018x}",
+        frame as *const TrapFrame as usize + size_of::<TrapFrame>()
+    );
+    log_backtrace(frame.elr as usize, frame.x29 as usize);
+    error!("-----------------------------");
+
+    panic!("Kernel oops: {}", esr.class_description());
+}
+
+/// Lets the exception return into the kernel loop, discarding the context of `frame`.
+pub fn set_return_to_kernel_loop(frame: &mut TrapFrame) {
+    frame.elr = kernel_loop_trampoline as *const () as u64;
+    frame.spsr = SPSR_EL1H;
 }
@@ -1,33 +1,50 @@
 use crate::aarch64::registers::read_esr_el1;
 use crate::{
-    aarch64::registers::{
-        daif::{mask_all, unmask_irq},
-        read_exception_source_el,
+    aarch64::{
+        generic_timer::handle_tick,
+        registers::{
+            current_core,
+            daif::{mask_all, unmask_irq},
+            read_exception_source_el,
+        },
     },
+    application_manager::schedule,
     get_current_el,
     interrupt_handlers::{
-        DISABLE_IRQ_BASE, ENABLE_IRQ_BASE, GPIO_PENDING_BIT_OFFSET, IRQ_PENDING_BASE,
+        TrapFrame, DISABLE_IRQ_BASE, ENABLE_IRQ_BASE, FIQ_CONTROL, GPIO_PENDING_BIT_OFFSET,
+        IRQ_PENDING_BASE,
     },
     peripherals::{
         gpio::{read_gpio_event_detect_status, reset_gpio_event_detect_status},
         uart::clear_uart_interrupt_state,
     },
-    read_address, write_address,
+    pi3::{
+        local_interrupt_controller::{pending_sources, source},
+        timer::{clear_compare, CompareChannel},
+    },
+    read_address,
+    smp::ipi::{take_pending_ipis, Ipi, IPI_MAILBOX},
+    sync::IrqSpinLock,
+    write_address,
 };
 use alloc::vec::Vec;
-use log::{debug, info};
+use log::{debug, error, info};
 
 struct InterruptHandlers {
     source: IRQSource,
     function: fn(),
 }
 
-// TODO: replace with hashmap and check for better alternatives for option
-static mut INTERRUPT_HANDLERS: Option<Vec<InterruptHandlers>> = None;
+// TODO: replace with hashmap
+static INTERRUPT_HANDLERS: IrqSpinLock<Vec<InterruptHandlers>> = IrqSpinLock::new(Vec::new());
 
 #[derive(Clone)]
 #[repr(u32)]
 pub enum IRQSource {
+    /// System timer compare register 1.
+    SystemTimer1 = 1,
+    /// System timer compare register 3.
+    SystemTimer3 = 3,
     AuxInt = 29,
     I2cSpiSlvInt = 44,
     Pwa0 = 45,
@@ -45,18 +62,81 @@ pub enum IRQSource {
 
 #[inline(always)]
 pub fn initialize_interrupt_handler() {
-    unsafe { INTERRUPT_HANDLERS = Some(Vec::new()) };
+    INTERRUPT_HANDLERS.lock().clear();
 }
 
 pub fn register_interrupt_handler(source: IRQSource, function: fn()) {
-    if let Some(handler_vec) = unsafe { &mut *core::ptr::addr_of_mut!(INTERRUPT_HANDLERS) } {
-        handler_vec.push(InterruptHandlers { source, function });
-    }
+    INTERRUPT_HANDLERS
+        .lock()
+        .push(InterruptHandlers { source, function });
+}
+
+/// Handlers of the IPIs, indexed by [`Ipi`].
+type IpiHandlers = [Option<fn()>; Ipi::ALL.len()];
+
+static IPI_HANDLERS: IrqSpinLock<IpiHandlers> = IrqSpinLock::new([None; Ipi::ALL.len()]);
+
+/// Registers `function` to run when the calling core receives `ipi`,
+/// replacing the previous handler.
+pub fn register_ipi_handler(ipi: Ipi, function: fn()) {
+    IPI_HANDLERS.lock()[ipi as usize] = Some(function);
+}
+
+/// Registered handler at `index`, the lock isn't held while handlers run.
+fn interrupt_handler(index: usize) -> Option<(IRQSource, fn())> {
+    INTERRUPT_HANDLERS
+        .lock()
+        .get(index)
+        .map(|handler| (handler.source.clone(), handler.function))
+}
+
+/// FIQ Handler
+///
+/// No interrupt source is routed to FIQ, so a FIQ is unexpected. Routing is
+/// disabled to keep a level-triggered source from firing over and over.
+#[no_mangle]
+unsafe extern "C" fn rust_fiq_handler(_frame: &mut TrapFrame) {
+    mask_all();
+    let control = unsafe { read_address(FIQ_CONTROL) };
+    error!(
+        "Unexpected FIQ from source {}, disabling FIQ routing",
+        control & 0x7F
+    );
+    unsafe { write_address(FIQ_CONTROL, 0) };
 }
 
 #[no_mangle]
-unsafe extern "C" fn rust_irq_handler() {
+unsafe extern "C" fn rust_irq_handler(frame: &mut TrapFrame) {
     mask_all();
+    let local_sources = pending_sources(current_core());
+    if local_sources & source::CNTPNS != 0 {
+        handle_tick();
+    }
+    if local_sources & source::mailbox(IPI_MAILBOX) != 0 {
+        handle_ipis();
+    }
+
+    // GPU interrupts are routed to core 0 only.
+    if local_sources & source::GPU != 0 {
+        handle_gpu_interrupts();
+    }
+
+    // A handler may have terminated the interrupted process.
+    if frame.from_el0() {
+        schedule(frame);
+    }
+}
+
+fn handle_ipis() {
+    for ipi in take_pending_ipis() {
+        let handler = IPI_HANDLERS.lock()[ipi as usize];
+        if let Some(handler) = handler {
+            handler();
+        }
+    }
+}
+
+fn handle_gpu_interrupts() {
     let pending_irqs = get_irq_pending_sources();
 
     if pending_irqs & GPIO_PENDING_BIT_OFFSET != 0 {
@@ -67,13 +147,15 @@ unsafe extern "C" fn rust_irq_handler() {
         debug!("Return register address: {:#x}", read_esr_el1());
     }
 
-    if let Some(handler_vec) = unsafe { &*core::ptr::addr_of_mut!(INTERRUPT_HANDLERS) } {
-        for handler in handler_vec {
-            if (pending_irqs & (1 << (handler.source.clone() as u32))) != 0 {
-                (handler.function)();
-                clear_interrupt_for_source(handler.source.clone());
-            }
+    let mut index = 0;
+    while let Some((source, function)) = interrupt_handler(index) {
+        if (pending_irqs & (1 << (source.clone() as u32))) != 0 {
+            // Acknowledged first, so an event raised while the handler runs,
+            // like a timer match it armed, isn't discarded.
+            clear_interrupt_for_source(source);
+            function();
         }
+        index += 1;
     }
 }
 
@@ -145,6 +227,8 @@ pub fn get_irq_pending_sources() -> u64 {
 fn clear_interrupt_for_source(source: IRQSource) {
     match source {
         IRQSource::UartInt => clear_uart_interrupt_state(),
+        IRQSource::SystemTimer1 => clear_compare(CompareChannel::C1),
+        IRQSource::SystemTimer3 => clear_compare(CompareChannel::C3),
         _ => {
             todo!()
         }
@@ -1,11 +1,20 @@
 use crate::{
-    aarch64::registers::{daif::mask_all, read_elr_el1, read_esr_el1, read_exception_source_el},
+    aarch64::registers::{
+        daif::mask_all, read_elr_el1, read_esr_el1, read_exception_source_el, read_far_el1,
+    },
+    application_manager::{
+        address_space::FaultAccess,
+        current_app_id, current_pid, exit_current, handle_page_fault,
+        process::{ExitStatus, Signal},
+        raise_fault_signal, schedule,
+    },
+    configuration::memory_mapping::{EL0_STACK_BOTTOM, EL0_STACK_GUARD_PAGE},
     get_current_el,
-    interrupt_handlers::{set_return_to_kernel_loop, EsrElX, TrapFrame},
-    pi3::mailbox,
+    interrupt_handlers::{EsrElX, TrapFrame},
+    syscalls,
 };
 
-use log::{debug, error, warn};
+use log::{debug, error};
 
 /// Synchronous Exception Handler
 ///
@@ -13,34 +22,102 @@ use log::{debug, error, warn};
 /// immediately lower than the target level is using
 /// AArch64.
 #[no_mangle]
-unsafe extern "C" fn rust_synchronous_interrupt_imm_lower_aarch64(frame: &mut TrapFrame) -> usize {
+unsafe extern "C" fn rust_synchronous_interrupt_imm_lower_aarch64(frame: &mut TrapFrame) {
     mask_all();
     let esr: EsrElX = EsrElX::from(read_esr_el1());
     debug!("Synchronous interrupt from lower EL triggered");
     log_sync_exception();
-    match esr.ec {
-        0b100100 => {
-            error!("Data Abort from a lower Exception level");
-            error!("Cause: {}", decode_data_abort(esr.iss as usize));
-        }
-        0b010101 => {
-            debug!("SVC instruction execution in AArch64");
-            return handle_svc(frame);
-        }
-        0b100010 => {
-            error!("PC alignment fault.");
-        }
-        _ => {
-            error!("Synchronous interrupt: Unknown Error Code: {:b}", esr.ec);
+
+    if esr.ec == 0b010101 {
+        debug!("SVC instruction execution in AArch64");
+        syscalls::dispatch(frame);
+        return;
+    }
+
+    let fault_address = read_far_el1() as usize;
+    if let Some(access) = demand_paging_access(esr) {
+        match handle_page_fault(fault_address, access) {
+            // Retry the access, now that the page is backed.
+            Ok(()) => return,
+            Err(err) => error!("Page fault at {:#x} not handled: {:?}", fault_address, err),
         }
     }
 
-    warn!("UnhandledException -> Returning to kernel...");
-    set_return_to_kernel_loop();
-    0
+    if esr.ec == 0b100100 && (EL0_STACK_GUARD_PAGE..EL0_STACK_BOTTOM).contains(&fault_address) {
+        error!(
+            "Stack overflow in app {}",
+            current_app_id().unwrap_or_default()
+        );
+    }
+    let signal = fault_signal(esr);
+    if !raise_fault_signal(signal) {
+        log_crash_report(frame, esr, fault_address);
+        exit_current(ExitStatus::Signaled(signal));
+    }
+    schedule(frame);
+}
+
+/// Access of a fault, which may be resolved by backing the page or by copying
+/// a page shared copy-on-write.
+fn demand_paging_access(esr: EsrElX) -> Option<FaultAccess> {
+    // Fault status codes 0b0001xx are translation faults and 0b0011xx
+    // permission faults on levels 0 to 3.
+    let translation_fault = esr.iss & 0b111100 == 0b000100;
+    let permission_fault = esr.iss & 0b111100 == 0b001100;
+    let write = esr.iss & (1 << 6) != 0;
+
+    match esr.ec {
+        0b100000 if translation_fault => Some(FaultAccess::Execute),
+        0b100100 if write && (translation_fault || permission_fault) => Some(FaultAccess::Write),
+        0b100100 if translation_fault => Some(FaultAccess::Read),
+        _ => None,
+    }
+}
+
+/// Signal raised for a process, which caused the exception `esr`.
+fn fault_signal(esr: EsrElX) -> Signal {
+    match esr.ec {
+        // Alignment faults are reported by the fault status code of aborts.
+        0b100000 | 0b100100 if esr.iss & 0b111111 == 0b100001 => Signal::SIGBUS,
+        0b100000 | 0b100100 => Signal::SIGSEGV,
+        0b100010 | 0b100110 => Signal::SIGBUS,
+        0b101000 | 0b101100 => Signal::SIGFPE,
+        0b110000 | 0b110010 | 0b110100 | 0b111000 | 0b111100 => Signal::SIGTRAP,
+        _ => Signal::SIGILL,
+    }
+}
+
+/// Logs why the current process crashed, together with its registers.
+pub(super) fn log_crash_report(frame: &TrapFrame, esr: EsrElX, fault_address: usize) {
+    error!(
+        "-------- Process {} (app {}) crashed --------",
+        current_pid().unwrap_or_default(),
+        current_app_id().unwrap_or_default()
+    );
+    error!("Exception: {}", esr.class_description());
+    match esr.ec {
+        0b100000 => error!("Cause: {}", decode_data_abort(esr.iss as usize)),
+        0b100100 => error!(
+            "Cause: {} on {}",
+            decode_data_abort(esr.iss as usize),
+            if esr.iss & (1 << 6) != 0 {
+                "write"
+            } else {
+                "read"
+            }
+        ),
+        _ => {}
+    }
+    error!("ESR: {:#010x}", read_esr_el1());
+    error!("ELR: {:#018x}", frame.elr);
+    if esr.has_fault_address() {
+        error!("FAR: {:#018x}", fault_address);
+    }
+    frame.log_registers();
+    error!("---------------------------------------------");
 }
 
-fn decode_data_abort(iss: usize) -> &'static str {
+pub(super) fn decode_data_abort(iss: usize) -> &'static str {
     match iss & 0b111111 {
         0b000000 => "Address size fault, level 0",
         0b000001 => "Address size fault, level 1",
@@ -89,21 +166,6 @@ fn decode_data_abort(iss: usize) -> &'static str {
     }
 }
 
-fn handle_svc(frame: &mut TrapFrame) -> usize {
-    match frame.x8 {
-        0 => {
-            debug!("Program exited!");
-            set_return_to_kernel_loop();
-            0
-        }
-        67 => {
-            let response = mailbox::read_soc_temp([0]).unwrap();
-            response[1] as usize
-        }
-        _ => 0,
-    }
-}
-
 fn log_sync_exception() {
     let source_el = read_exception_source_el() >> 2;
     debug!("--------Sync Exception in EL{}--------", source_el);
@@ -4,24 +4,39 @@
 extern crate alloc;
 
 use core::{
+    alloc::{GlobalAlloc, Layout},
     arch::asm,
     panic::PanicInfo,
     ptr::{read_volatile, write_volatile},
 };
 use log::LevelFilter;
-use log::{Level, Metadata, Record};
+use log::{warn, Level, Metadata, Record};
 
 use heap::Heap;
 
 use crate::{
-    aarch64::mmu::{
-        allocate_memory, PhysSource, KERNEL_VIRTUAL_MEM_SPACE, LEVEL2_BLOCK_SIZE, NORMAL_MEM, UXN,
-        WRITABLE,
+    aarch64::{
+        backtrace::{Backtrace, Location, MAX_DEPTH},
+        generic_timer::start_tick,
+        mmu::{
+            allocate_memory, PhysSource, KERNEL_VIRTUAL_MEM_SPACE, LEVEL2_BLOCK_SIZE, NORMAL_MEM,
+            UXN, WRITABLE,
+        },
     },
     application_manager::initialize_app_manager,
     console::{flush_terminal, init_terminal},
+    cpufreq::initialize_cpufreq,
+    idle::initialize_idle,
     interrupt_handlers::irq::initialize_interrupt_handler,
-    pi3::timer::sleep_s,
+    peripherals::rng::rng_init,
+    smp::{
+        ipi::{enable_ipis, halt_other_cores, initialize_ipi_handlers},
+        mark_core_ready,
+    },
+    sync::IrqSpinLock,
+    syscalls::user_access::initialize_pan,
+    time::{wall_clock_is_set, DateTime, Duration, Instant},
+    timer::initialize_timer_service,
 };
 
 static LOGGER: UartLogger = UartLogger;
@@ -32,22 +47,53 @@ unsafe extern "C" {
 }
 
 #[global_allocator]
-pub static mut GLOBAL_ALLOCATOR: Heap = Heap::empty();
+pub static GLOBAL_ALLOCATOR: KernelHeap = KernelHeap {
+    heap: IrqSpinLock::new(Heap::empty()),
+};
+
+/// Heap shared by all cores and interrupt handlers.
+pub struct KernelHeap {
+    heap: IrqSpinLock<Heap>,
+}
+
+unsafe impl GlobalAlloc for KernelHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        unsafe { self.heap.lock().alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { self.heap.lock().dealloc(ptr, layout) }
+    }
+}
 
 pub unsafe fn initialize_kernel_heap() {
     let start = core::ptr::addr_of_mut!(__kernel_end) as usize | KERNEL_VIRTUAL_MEM_SPACE;
     let size = LEVEL2_BLOCK_SIZE * 2;
 
     allocate_memory(start, size, PhysSource::Any, NORMAL_MEM | UXN | WRITABLE).unwrap();
-    let heap = core::ptr::addr_of_mut!(GLOBAL_ALLOCATOR);
-    (*heap).init(start, start + size);
+    GLOBAL_ALLOCATOR.heap.lock().init(start, start + size);
 }
 
 #[panic_handler]
 fn panic(_panic: &PanicInfo) -> ! {
+    halt_other_cores();
+    // Captured on the stack, the panic may come from within the heap lock.
+    let mut backtrace = [0; MAX_DEPTH];
+    let mut frames = 0;
+    for (slot, address) in backtrace.iter_mut().zip(Backtrace::current()) {
+        *slot = address;
+        frames += 1;
+    }
     loop {
         println!("Panic: {}", _panic.message());
-        sleep_s(1);
+        if let Some(location) = _panic.location() {
+            println!("at {}", location);
+        }
+        println!("Backtrace:");
+        for (depth, address) in backtrace[..frames].iter().enumerate() {
+            println!("  #{:<2} {}", depth, Location(*address));
+        }
+        idle::sleep(Duration::from_secs(1));
     }
 }
 
@@ -56,11 +102,18 @@ pub mod peripherals;
 pub mod aarch64;
 pub mod configuration;
 pub mod framebuffer;
+pub mod idle;
 pub mod interrupt_handlers;
 
 pub mod application_manager;
 pub mod console;
+pub mod cpufreq;
 pub mod pi3;
+pub mod smp;
+pub mod sync;
+pub mod syscalls;
+pub mod time;
+pub mod timer;
 
 #[inline(always)]
 pub unsafe fn read_address(address: u32) -> u32 {
@@ -84,13 +137,36 @@ pub fn get_current_el() -> u64 {
     el >> 2
 }
 
+/// Rate of the per-core tick of the generic timer.
+const TICK_HZ: u64 = 100;
+
 pub fn initialize_kernel() {
+    // First of all, as panics wait for events.
+    initialize_idle();
     unsafe { initialize_kernel_heap() };
+    initialize_pan();
+    rng_init();
     initialize_interrupt_handler();
+    initialize_ipi_handlers();
+    enable_ipis();
+    initialize_timer_service();
+    start_tick(TICK_HZ);
+    if let Err(err) = initialize_cpufreq() {
+        warn!("Clock scaling unavailable: {:?}", err);
+    }
     initialize_app_manager();
     init_terminal();
 }
 
+/// Initializes a secondary core once it runs in EL1, see [`smp`].
+pub fn initialize_secondary_core() {
+    initialize_idle();
+    initialize_pan();
+    enable_ipis();
+    start_tick(TICK_HZ);
+    mark_core_ready();
+}
+
 struct UartLogger;
 
 impl log::Log for UartLogger {
@@ -100,7 +176,14 @@ impl log::Log for UartLogger {
 
     fn log(&self, record: &Record) {
         if self.enabled(record.metadata()) {
-            println!("{} - {}", record.level(), record.args());
+            // Calendar time is only meaningful once the wall clock has been set.
+            if wall_clock_is_set() {
+                let now = DateTime::now();
+                println!("[{:.6}] {} - {}", now, record.level(), record.args());
+            } else {
+                let now = Instant::now();
+                println!("[{:>12}] {} - {}", now, record.level(), record.args());
+            }
             if record.level() <= Level::Info {
                 flush_terminal();
             }
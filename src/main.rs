@@ -7,16 +7,19 @@ use core::{
     ptr::write_volatile,
 };
 use log::{debug, info};
+use spin::Mutex;
 
 extern crate alloc;
 
 use alloc::{slice, vec::Vec};
 use nova::{
     aarch64::registers::{daif, read_id_aa64mmfr0_el1},
-    application_manager::{add_app, Application},
-    configuration::memory_mapping::initialize_mmu_translation_tables,
+    application_manager::{add_app, has_ready_processes, run_ready_processes},
+    configuration::memory_mapping::{el1_stack_top, initialize_mmu_translation_tables},
     framebuffer::{FrameBuffer, BLUE, GREEN, RED},
-    get_current_el, init_logger,
+    get_current_el,
+    idle::{halt, idle, initialize_idle_el2},
+    init_logger,
     interrupt_handlers::irq::{enable_irq_source, IRQSource},
     peripherals::{
         gpio::{
@@ -25,17 +28,21 @@ use nova::{
         },
         uart::uart_init,
     },
-    pi3::timer::sleep_s,
     print, println,
+    smp::{start_secondary_cores, CORE_COUNT},
+    syscalls::user::{exit, nanosleep, read_soc_temp},
 };
 
 global_asm!(include_str!("vector.S"));
 global_asm!(include_str!("config.S"));
+global_asm!(include_str!("user_access.S"));
 
-static mut FRAMEBUFFER: Option<FrameBuffer> = None;
+// Drawn on from EL0, which can't mask interrupts, so no `IrqSpinLock`.
+static FRAMEBUFFER: Mutex<Option<FrameBuffer>> = Mutex::new(None);
 
 extern "C" {
     fn el2_to_el1();
+    fn enter_el1(stack_top: usize, entry: usize);
     fn configure_mmu_el1();
     static mut __bss_start: u32;
     static mut __bss_end: u32;
@@ -53,11 +60,28 @@ pub unsafe extern "C" fn _start() {
     );
 }
 
+/// Entry point of the secondary cores, released from the spin table.
+#[no_mangle]
+pub unsafe extern "C" fn _start_secondary() {
+    // Set the stack pointer to the EL2 stack of the core
+    asm!(
+        "mrs x0, MPIDR_EL1",
+        "and x0, x0, #0xFF",
+        "ldr x1, =__secondary_stacks_start",
+        "mov x2, #0x4000",
+        "madd x1, x0, x2, x1",
+        "mov sp, x1",
+        "b secondary_main",
+        options(noreturn)
+    );
+}
+
 #[no_mangle]
 pub extern "C" fn main() -> ! {
     unsafe {
         zero_bss();
     }
+    initialize_idle_el2();
     enable_uart();
 
     // Set ACT Led to Outout
@@ -74,13 +98,26 @@ pub extern "C" fn main() -> ! {
 
     debug!("Register: AA64MMFR0_EL1: {:064b}", read_id_aa64mmfr0_el1());
     info!("Moving El2->EL1");
-    unsafe { FRAMEBUFFER = Some(FrameBuffer::default()) };
+    *FRAMEBUFFER.lock() = Some(FrameBuffer::default());
 
     unsafe {
         el2_to_el1();
     }
-    #[allow(clippy::empty_loop)]
-    loop {}
+    halt();
+}
+
+/// EL2 setup of a secondary core, core 0 already initialized the translation tables.
+#[no_mangle]
+pub extern "C" fn secondary_main(core: usize) -> ! {
+    initialize_idle_el2();
+    unsafe {
+        configure_mmu_el1();
+        enter_el1(
+            el1_stack_top(core),
+            secondary_kernel_main as *const () as usize,
+        );
+    }
+    halt();
 }
 
 unsafe fn zero_bss() {
@@ -104,16 +141,33 @@ pub extern "C" fn kernel_main() {
     debug!("heap allocation test: {:?}", test_vector);
     enable_irq_source(IRQSource::UartInt);
 
-    let app = Application::new(el0 as *const () as usize);
-    add_app(app).unwrap();
+    add_app(el0 as *const () as usize).unwrap();
+
+    let cores = start_secondary_cores(_start_secondary as *const () as usize);
+    info!("{} of {} cores running", cores, CORE_COUNT);
 
     kernel_loop();
 }
+
 #[no_mangle]
-pub extern "C" fn kernel_loop() {
-    daif::unmask_all();
-    #[allow(clippy::empty_loop)]
-    loop {}
+pub extern "C" fn secondary_kernel_main() -> ! {
+    nova::initialize_secondary_core();
+    kernel_loop();
+}
+#[no_mangle]
+pub extern "C" fn kernel_loop() -> ! {
+    loop {
+        // IRQs stay masked from the check until `wfi`, so a wakeup in between
+        // still ends the wait.
+        daif::mask_irq();
+        if has_ready_processes() {
+            daif::unmask_all();
+            run_ready_processes();
+        } else {
+            idle();
+            daif::unmask_all();
+        }
+    }
 }
 #[no_mangle]
 pub unsafe extern "C" fn el0(argc: usize, argv: *const *const u8) {
@@ -135,7 +189,7 @@ pub unsafe extern "C" fn el0(argc: usize, argv: *const *const u8) {
         })
         .next();
 
-    sleep_s(1);
+    nanosleep(1_000_000_000);
 
     // Set GPIO 26 to Input
     enable_irq_source(IRQSource::GpioInt0); //26 is on the first GPIO bank
@@ -143,7 +197,7 @@ pub unsafe extern "C" fn el0(argc: usize, argv: *const *const u8) {
     gpio_pull_up(26);
     set_falling_edge_detect(26, true);
 
-    if let Some(fb) = unsafe { FRAMEBUFFER.as_mut() } {
+    if let Some(fb) = FRAMEBUFFER.lock().as_mut() {
         for i in 0..1080 {
             fb.draw_pixel(50, i, BLUE);
         }
@@ -156,7 +210,7 @@ pub unsafe extern "C" fn el0(argc: usize, argv: *const *const u8) {
         fb.draw_function(cos, 0, 101, RED);
     }
 
-    let _temp = syscall(67);
+    let _temp = read_soc_temp();
 
     if let Some(num) = first_arg.and_then(|val| val.parse::<usize>().ok()) {
         println!("Calculting prime to: {}", num);
@@ -181,7 +235,7 @@ pub unsafe extern "C" fn el0(argc: usize, argv: *const *const u8) {
     }
 
     blink_gpio(SpecificGpio::OnboardLed as u8, 500);
-    syscall(0);
+    exit(0);
 }
 
 fn cos(x: u32) -> f64 {
@@ -194,17 +248,3 @@ fn enable_uart() {
     let _ = set_gpio_function(15, GPIOFunction::Alternative0);
     uart_init();
 }
-
-pub fn syscall(nr: u64) -> u64 {
-    let ret: u64;
-
-    unsafe {
-        asm!(
-            "svc #0",
-            in("x8") nr,
-            lateout("x0") ret,
-        );
-    }
-
-    ret
-}
@@ -1,3 +1,4 @@
 pub mod gpio;
+pub mod rng;
 #[macro_use]
 pub mod uart;
@@ -0,0 +1,37 @@
+use core::hint::spin_loop;
+
+use crate::{read_address, write_address};
+
+const RNG_CTRL: u32 = 0x3F10_4000;
+const RNG_CTRL_RBGEN: u32 = 1 << 0;
+
+const RNG_STATUS: u32 = 0x3F10_4004;
+/// Number of initial numbers to discard, while the generator warms up.
+const RNG_WARMUP_COUNT: u32 = 0x4_0000;
+
+const RNG_DATA: u32 = 0x3F10_4008;
+
+/// Initialize the hardware random number generator
+pub fn rng_init() {
+    unsafe {
+        write_address(RNG_STATUS, RNG_WARMUP_COUNT);
+        write_address(RNG_CTRL, read_address(RNG_CTRL) | RNG_CTRL_RBGEN);
+    }
+}
+
+/// Read a random number, blocks until one is available
+pub fn read_random_u32() -> u32 {
+    // STATUS[31:24] holds the number of words available
+    while unsafe { read_address(RNG_STATUS) } >> 24 == 0 {
+        spin_loop();
+    }
+    unsafe { read_address(RNG_DATA) }
+}
+
+/// Fill `buffer` with random bytes
+pub fn fill_random(buffer: &mut [u8]) {
+    for chunk in buffer.chunks_mut(4) {
+        let bytes = read_random_u32().to_le_bytes();
+        chunk.copy_from_slice(&bytes[..chunk.len()]);
+    }
+}
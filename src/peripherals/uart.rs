@@ -1,9 +1,6 @@
-use core::{
-    arch::asm,
-    fmt::{self, Write},
-};
+use core::fmt::{self, Write};
 
-use crate::{read_address, write_address};
+use crate::{idle::wait_until, read_address, write_address};
 
 const BAUD: u32 = 115200;
 const UART_CLK: u32 = 48_000_000;
@@ -32,16 +29,20 @@ const UART0_ICR: u32 = 0x3F20_1044;
 
 pub struct Uart;
 
-impl Write for Uart {
-    fn write_str(&mut self, s: &str) -> core::fmt::Result {
-        for byte in s.bytes() {
-            while (unsafe { read_address(UART0_FR) } & UART0_FR_TXFF) != 0 {
-                unsafe { asm!("nop") }
-            }
+impl Uart {
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            wait_until(|| unsafe { read_address(UART0_FR) } & UART0_FR_TXFF == 0);
             unsafe { write_address(UART0_DR, byte as u32) };
         }
         // wait till uart is not busy anymore
-        while ((unsafe { read_address(UART0_FR) } >> 3) & 0b1) != 0 {}
+        wait_until(|| (unsafe { read_address(UART0_FR) } >> 3) & 0b1 == 0);
+    }
+}
+
+impl Write for Uart {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.write_bytes(s.as_bytes());
         Ok(())
     }
 }
@@ -67,6 +68,11 @@ pub fn _print(args: fmt::Arguments) {
     let _ = Uart.write_fmt(args);
 }
 
+/// Writes raw bytes to the console, which need not be valid UTF-8.
+pub fn write_console(bytes: &[u8]) {
+    Uart.write_bytes(bytes);
+}
+
 /// Initialize UART peripheral
 pub fn uart_init() {
     let baud_div_times_64 = (UART_CLK * 4) / BAUD;
@@ -0,0 +1,74 @@
+//! BCM2836 core-local interrupt controller.
+//!
+//! Routes the per-core interrupts, like the generic timer and the core
+//! mailboxes, as well as the GPU interrupts to the cores.
+
+use crate::{read_address, write_address};
+
+/// Base address of the local peripherals, above the GPU peripherals.
+pub const LOCAL_PERIPHERAL_BASE: u32 = 0x4000_0000;
+
+/// Core timers interrupt control, one register per core.
+const CORE_TIMER_INTERRUPT_CONTROL: u32 = LOCAL_PERIPHERAL_BASE + 0x40;
+/// Core mailboxes interrupt control, one register per core.
+const CORE_MAILBOX_INTERRUPT_CONTROL: u32 = LOCAL_PERIPHERAL_BASE + 0x50;
+/// Core IRQ source, one register per core.
+const CORE_IRQ_SOURCE: u32 = LOCAL_PERIPHERAL_BASE + 0x60;
+/// Write-set registers of the four mailboxes of each core.
+const CORE_MAILBOX_SET: u32 = LOCAL_PERIPHERAL_BASE + 0x80;
+/// Read and write-clear registers of the four mailboxes of each core.
+const CORE_MAILBOX_CLEAR: u32 = LOCAL_PERIPHERAL_BASE + 0xC0;
+
+/// Interrupt sources of a core, as reported by [`pending_sources`].
+pub mod source {
+    pub const CNTPS: u32 = 1 << 0;
+    pub const CNTPNS: u32 = 1 << 1;
+    pub const CNTHP: u32 = 1 << 2;
+    pub const CNTV: u32 = 1 << 3;
+    /// Mailbox `n` of the core, for `n` in `0..4`.
+    pub const fn mailbox(n: usize) -> u32 {
+        1 << (4 + n)
+    }
+    /// Any of the GPU interrupts, see `IRQ_PENDING_BASE`.
+    pub const GPU: u32 = 1 << 8;
+}
+
+/// Routes the non-secure physical timer of `core` to its IRQ line.
+pub fn enable_physical_timer_irq(core: usize) {
+    let register = CORE_TIMER_INTERRUPT_CONTROL + 4 * core as u32;
+    unsafe { write_address(register, read_address(register) | source::CNTPNS) };
+}
+
+pub fn disable_physical_timer_irq(core: usize) {
+    let register = CORE_TIMER_INTERRUPT_CONTROL + 4 * core as u32;
+    unsafe { write_address(register, read_address(register) & !source::CNTPNS) };
+}
+
+/// Pending interrupt sources of `core`.
+pub fn pending_sources(core: usize) -> u32 {
+    unsafe { read_address(CORE_IRQ_SOURCE + 4 * core as u32) }
+}
+
+/// Raises the IRQ of `core` while `mailbox` holds set bits.
+pub fn enable_mailbox_irq(core: usize, mailbox: usize) {
+    let register = CORE_MAILBOX_INTERRUPT_CONTROL + 4 * core as u32;
+    unsafe { write_address(register, read_address(register) | 1 << mailbox) };
+}
+
+/// Sets `bits` in `mailbox` of `core`, the other bits are left alone.
+pub fn set_mailbox(core: usize, mailbox: usize, bits: u32) {
+    unsafe { write_address(mailbox_register(CORE_MAILBOX_SET, core, mailbox), bits) };
+}
+
+pub fn read_mailbox(core: usize, mailbox: usize) -> u32 {
+    unsafe { read_address(mailbox_register(CORE_MAILBOX_CLEAR, core, mailbox)) }
+}
+
+/// Clears `bits` in `mailbox` of `core`.
+pub fn clear_mailbox(core: usize, mailbox: usize, bits: u32) {
+    unsafe { write_address(mailbox_register(CORE_MAILBOX_CLEAR, core, mailbox), bits) };
+}
+
+fn mailbox_register(base: u32, core: usize, mailbox: usize) -> u32 {
+    base + 16 * core as u32 + 4 * mailbox as u32
+}
@@ -2,7 +2,8 @@ use core::slice;
 
 use crate::{
     aarch64::mmu::GRANULARITY, configuration::memory_mapping::MAILBOX_PHYSICAL_ADDRESS,
-    configuration::memory_mapping::MAILBOX_VIRTUAL_ADDRESS, read_address, write_address,
+    configuration::memory_mapping::MAILBOX_VIRTUAL_ADDRESS, idle::wait_until, read_address,
+    sync::IrqSpinLock, write_address,
 };
 use nova_error::NovaError;
 
@@ -19,6 +20,15 @@ const MBOX_WRITE: u32 = MBOX_BASE + 0x20;
 const MAIL_FULL: u32 = 0x80000000;
 const MAIL_EMPTY: u32 = 0x40000000;
 
+/// Clock id of the ARM cores.
+pub const CLOCK_ARM: u32 = 3;
+/// Voltage id of the VideoCore and ARM cores.
+pub const VOLTAGE_CORE: u32 = 1;
+
+/// Serializes the mailbox transactions between cores, and with them the use
+/// of the shared property buffer.
+static PROPERTY_BUFFER: IrqSpinLock<()> = IrqSpinLock::new(());
+
 const HEADER_LENGTH: usize = 4 + 4 + 4 + 4 + 4; // Total Size + Request + Tag + MaxBufferLength + RequestLength
 const FOOTER_LENGTH: usize = 4;
 
@@ -36,6 +46,7 @@ macro_rules! mailbox_command {
         pub fn $name(
             request_data: [u32; $request_len / 4],
         ) -> Result<[u32; $response_len / 4], NovaError> {
+            let _mailbox = PROPERTY_BUFFER.lock();
             let mailbox = unsafe {
                 slice::from_raw_parts_mut(MAILBOX_VIRTUAL_ADDRESS as *mut u32, GRANULARITY / 4)
             };
@@ -50,9 +61,7 @@ macro_rules! mailbox_command {
 
             //let addr = core::ptr::addr_of!(mailbox[0]) as u32;
 
-            write_mailbox(8, unsafe { MAILBOX_PHYSICAL_ADDRESS.unwrap() } as u32);
-
-            let _ = read_mailbox(8);
+            let _ = exchange(8, unsafe { MAILBOX_PHYSICAL_ADDRESS.unwrap() } as u32);
 
             if mailbox[1] == 0 {
                 return Err(NovaError::Mailbox);
@@ -67,13 +76,38 @@ macro_rules! mailbox_command {
 
 mailbox_command!(read_soc_temp, 0x0003_0006, 4, 8);
 
+// Clocks, the rates are in Hz
+mailbox_command!(get_clock_rate, 0x0003_0002, 4, 8);
+mailbox_command!(get_max_clock_rate, 0x0003_0004, 4, 8);
+mailbox_command!(get_min_clock_rate, 0x0003_0007, 4, 8);
+mailbox_command!(set_clock_rate, 0x0003_8002, 12, 8);
+
+// Voltages, in µV
+mailbox_command!(get_voltage, 0x0003_0003, 4, 8);
+mailbox_command!(set_voltage, 0x0003_8003, 8, 8);
+
 // Framebuffer
 mailbox_command!(get_display_resolution, 0x0004_0003, 0, 8);
 
-pub fn read_mailbox(channel: u32) -> u32 {
+/// Sends `data` on `channel` and returns the reply.
+///
+/// The transaction holds the mailbox, so replies can't be mixed up with the
+/// ones of other cores.
+pub fn call_mailbox(channel: u32, data: u32) -> u32 {
+    let _mailbox = PROPERTY_BUFFER.lock();
+    exchange(channel, data)
+}
+
+/// Sends `data` on `channel` and returns the reply, the mailbox has to be held.
+fn exchange(channel: u32, data: u32) -> u32 {
+    write_mailbox(channel, data);
+    read_mailbox(channel)
+}
+
+fn read_mailbox(channel: u32) -> u32 {
     // Wait until mailbox is not empty
     loop {
-        while unsafe { read_address(MBOX_STATUS) } & MAIL_EMPTY != 0 {}
+        wait_until(|| unsafe { read_address(MBOX_STATUS) } & MAIL_EMPTY == 0);
         let mut data = unsafe { read_address(MBOX_READ) };
         let read_channel = data & 0xF;
 
@@ -85,7 +119,7 @@ pub fn read_mailbox(channel: u32) -> u32 {
     }
 }
 
-pub fn write_mailbox(channel: u32, data: u32) {
-    while unsafe { read_address(MBOX_STATUS) } & MAIL_FULL != 0 {}
+fn write_mailbox(channel: u32, data: u32) {
+    wait_until(|| unsafe { read_address(MBOX_STATUS) } & MAIL_FULL == 0);
     unsafe { write_address(MBOX_WRITE, (data & !0xF) | (channel & 0xF)) };
 }
@@ -1,3 +1,4 @@
+pub mod local_interrupt_controller;
 pub mod mailbox;
 pub mod power_management;
 pub mod timer;
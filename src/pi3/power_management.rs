@@ -1,6 +1,6 @@
 use core::ptr::{read_volatile, write_volatile};
 
-use crate::PERIPHERAL_BASE;
+use crate::{idle::halt, PERIPHERAL_BASE};
 
 /// Power Management Base
 static PM_BASE: u32 = PERIPHERAL_BASE as u32 + 0x10_0000;
@@ -23,6 +23,6 @@ pub fn reboot_system() {
             PM_PASSWORD | (pm_rstc_val & PM_RSTC_WRCFG_CLR) | PM_RSTC_WRCFG_FULL_RESET,
         );
     }
-    #[allow(clippy::empty_loop)]
-    loop {}
+    // The watchdog resets the system within a tick.
+    halt();
 }
@@ -1,8 +1,19 @@
 use core::{hint::spin_loop, ptr::read_volatile};
 
+use crate::write_address;
+
+const TIMER_CS: u32 = 0x3F00_3000;
+
 const TIMER_CLOCK_LO: u32 = 0x3F00_3004;
 const TIMER_CLOCK_HI: u32 = 0x3F00_3008;
 
+/// Compare register 0, followed by the registers 1 to 3.
+const TIMER_C0: u32 = 0x3F00_300C;
+
+/// Smallest distance of a compare value to the current time, so the timer
+/// doesn't pass it while it is being written.
+const MIN_COMPARE_DISTANCE_US: u64 = 10;
+
 fn read_timer_32() -> u32 {
     unsafe { read_volatile(TIMER_CLOCK_LO as *const u32) }
 }
@@ -20,6 +31,46 @@ fn read_timer_64() -> u64 {
     }
 }
 
+/// Microseconds since the system timer started.
+pub fn current_time_us() -> u64 {
+    read_timer_64()
+}
+
+/// Compare registers of the system timer, which are free for the ARM cores.
+///
+/// Registers 0 and 2 are used by the GPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum CompareChannel {
+    C1 = 1,
+    C3 = 3,
+}
+
+impl CompareChannel {
+    fn register(self) -> u32 {
+        TIMER_C0 + 4 * self as u32
+    }
+
+    /// Match flag in `TIMER_CS`, written to acknowledge the match.
+    fn match_flag(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+/// Raises the IRQ of `channel` once the system timer reaches `deadline_us`.
+///
+/// Deadlines in the past fire right away. Only the lower 32 bits are compared,
+/// so deadlines more than ~71 minutes ahead fire early.
+pub fn set_compare(channel: CompareChannel, deadline_us: u64) {
+    let deadline = deadline_us.max(read_timer_64() + MIN_COMPARE_DISTANCE_US);
+    unsafe { write_address(channel.register(), deadline as u32) };
+}
+
+/// Acknowledges a match of `channel`.
+pub fn clear_compare(channel: CompareChannel) {
+    unsafe { write_address(TIMER_CS, channel.match_flag()) };
+}
+
 /// Sleep for `us` microseconds
 pub fn sleep_us(us: u64) {
     if us < u32::MAX as u64 {
@@ -0,0 +1,108 @@
+//! Bring-up of the secondary cores.
+//!
+//! The firmware parks cores 1 to 3 in a loop waiting for an entry point in the
+//! spin table at `0xd8`. Each core is released by writing the physical address
+//! of its entry there, it then boots through EL2 like core 0 and reports itself
+//! ready once it runs in EL1.
+
+use core::{
+    arch::asm,
+    ptr::write_volatile,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use log::{info, warn};
+
+use crate::{
+    aarch64::{
+        mmu::{PhysAddr, KERNEL_VIRTUAL_MEM_SPACE},
+        registers::current_core,
+    },
+    configuration::memory_mapping::SPIN_TABLE_VIRTUAL_ADDRESS,
+    idle::wait_for_event,
+    time::{Duration, Instant},
+};
+
+/// Number of cores of the BCM2837.
+pub const CORE_COUNT: usize = 4;
+
+/// Set of cores, bit `n` stands for core `n`.
+pub type CpuMask = u64;
+
+pub const ALL_CORES: CpuMask = (1 << CORE_COUNT) - 1;
+
+/// Offset of the spin table entry of core 1 in its page, followed by the
+/// entries of cores 2 and 3.
+const SPIN_TABLE_OFFSET: usize = 0xe0;
+
+/// Time a released core has to report itself ready.
+const STARTUP_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Whether each core runs the kernel, core 0 is the boot core.
+static CORE_READY: [AtomicBool; CORE_COUNT] = [
+    AtomicBool::new(true),
+    AtomicBool::new(false),
+    AtomicBool::new(false),
+    AtomicBool::new(false),
+];
+
+/// Releases the secondary cores to `entry`, the address of their EL2 entry
+/// point in the kernel image, and waits for each to report ready.
+///
+/// Returns the number of cores running the kernel.
+pub fn start_secondary_cores(entry: usize) -> usize {
+    // The cores start with the MMU off, so they need the physical address.
+    let entry: PhysAddr = entry & !KERNEL_VIRTUAL_MEM_SPACE;
+
+    for core in 1..CORE_COUNT {
+        let slot = (SPIN_TABLE_VIRTUAL_ADDRESS + SPIN_TABLE_OFFSET + 8 * (core - 1)) as *mut u64;
+        unsafe {
+            write_volatile(slot, entry as u64);
+            // The parked core reads the slot with its caches off.
+            asm!("dc civac, {}", "dsb sy", "sev", in(reg) slot);
+        }
+
+        let deadline = Instant::after(STARTUP_TIMEOUT);
+        while !is_core_ready(core) && !deadline.has_passed() {
+            core::hint::spin_loop();
+        }
+
+        if is_core_ready(core) {
+            info!("Core {} ready", core);
+        } else {
+            warn!("Core {} did not start", core);
+        }
+    }
+
+    ready_cores()
+}
+
+/// Reports the calling core ready, the last step of its bring-up.
+pub fn mark_core_ready() {
+    CORE_READY[current_core()].store(true, Ordering::Release);
+}
+
+pub fn is_core_ready(core: usize) -> bool {
+    CORE_READY
+        .get(core)
+        .is_some_and(|ready| ready.load(Ordering::Acquire))
+}
+
+/// Number of cores running the kernel.
+pub fn ready_cores() -> usize {
+    (0..CORE_COUNT).filter(|core| is_core_ready(*core)).count()
+}
+
+/// Running cores within `mask`.
+pub fn ready_cores_in(mask: CpuMask) -> impl Iterator<Item = usize> {
+    (0..CORE_COUNT).filter(move |core| mask & (1 << core) != 0 && is_core_ready(*core))
+}
+
+/// Parks the calling core for good, it only wakes for events.
+pub fn park() -> ! {
+    loop {
+        wait_for_event();
+    }
+}
+
+pub mod ipi;
@@ -0,0 +1,118 @@
+//! Inter-processor interrupts, sent through mailbox 0 of the target core.
+//!
+//! Every kind of IPI is one bit of the mailbox, so IPIs of the same kind which
+//! are sent before the target handles them are coalesced.
+
+use alloc::collections::vec_deque::VecDeque;
+use nova_error::NovaError;
+
+use crate::{
+    aarch64::registers::{current_core, daif},
+    interrupt_handlers::irq::register_ipi_handler,
+    pi3::local_interrupt_controller::{
+        clear_mailbox, enable_mailbox_irq, read_mailbox, set_mailbox,
+    },
+    smp::{is_core_ready, park, CORE_COUNT},
+    sync::{IrqSpinLock, PerCpu},
+};
+
+/// Mailbox of each core used for IPIs.
+pub const IPI_MAILBOX: usize = 0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum Ipi {
+    /// Asks the core to pick the next process.
+    Reschedule = 0,
+    /// Runs the functions queued by [`smp_call_function`].
+    CallFunction = 1,
+    /// Stops the core for good.
+    Halt = 2,
+}
+
+impl Ipi {
+    pub const ALL: [Ipi; 3] = [Ipi::Reschedule, Ipi::CallFunction, Ipi::Halt];
+
+    fn bit(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+/// Functions queued for a core by [`smp_call_function`].
+type CallQueue = IrqSpinLock<VecDeque<fn()>>;
+
+static CALL_QUEUES: PerCpu<CallQueue> =
+    PerCpu::new([const { IrqSpinLock::new(VecDeque::new()) }; CORE_COUNT]);
+
+/// Registers the handlers of the IPIs the kernel handles itself.
+pub fn initialize_ipi_handlers() {
+    register_ipi_handler(Ipi::CallFunction, run_queued_calls);
+    register_ipi_handler(Ipi::Halt, halt);
+}
+
+/// Lets the calling core receive IPIs.
+pub fn enable_ipis() {
+    enable_mailbox_irq(current_core(), IPI_MAILBOX);
+}
+
+pub fn send_ipi(core: usize, ipi: Ipi) {
+    set_mailbox(core, IPI_MAILBOX, ipi.bit());
+}
+
+/// Sends `ipi` to every running core but the calling one.
+pub fn send_ipi_to_others(ipi: Ipi) {
+    let current = current_core();
+    for core in (0..CORE_COUNT).filter(|core| *core != current && is_core_ready(*core)) {
+        send_ipi(core, ipi);
+    }
+}
+
+/// Acknowledges the pending IPIs of the calling core and returns them.
+pub fn take_pending_ipis() -> impl Iterator<Item = Ipi> {
+    let core = current_core();
+    let pending = read_mailbox(core, IPI_MAILBOX);
+    clear_mailbox(core, IPI_MAILBOX, pending);
+
+    Ipi::ALL
+        .into_iter()
+        .filter(move |ipi| pending & ipi.bit() != 0)
+}
+
+/// Runs `function` on `core` from its IRQ handler.
+///
+/// Returns once the call has been queued, functions for the calling core run
+/// right away.
+pub fn smp_call_function(core: usize, function: fn()) -> Result<(), NovaError> {
+    if !is_core_ready(core) {
+        return Err(NovaError::General("Core is not running."));
+    }
+    if core == current_core() {
+        function();
+        return Ok(());
+    }
+
+    CALL_QUEUES.for_core(core).lock().push_back(function);
+    send_ipi(core, Ipi::CallFunction);
+    Ok(())
+}
+
+/// Stops all other cores, for a panic.
+pub fn halt_other_cores() {
+    send_ipi_to_others(Ipi::Halt);
+}
+
+fn run_queued_calls() {
+    // The lock is released before each call, which may queue further calls.
+    loop {
+        let function = CALL_QUEUES.get().lock().pop_front();
+        match function {
+            Some(function) => function(),
+            None => break,
+        }
+    }
+}
+
+fn halt() {
+    daif::mask_all();
+    park();
+}
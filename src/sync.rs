@@ -0,0 +1,186 @@
+//! Synchronization primitives of the kernel.
+//!
+//! Locks taken by both interrupt handlers and regular kernel code mask IRQs
+//! while held, otherwise an IRQ taking a lock its core already holds spins
+//! forever. Between cores they wait in `wfe` for the holder to release the lock.
+
+use core::{
+    cell::UnsafeCell,
+    mem::ManuallyDrop,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use spin::{Mutex, MutexGuard};
+
+use crate::{
+    aarch64::registers::{current_core, daif},
+    idle::{send_event, wait_for_event},
+    smp::CORE_COUNT,
+};
+
+/// Spinlock which masks IRQs and FIQs on its core while it is held.
+///
+/// Kernel code only, masking interrupts traps in EL0.
+pub struct IrqSpinLock<T> {
+    inner: Mutex<T>,
+}
+
+impl<T> IrqSpinLock<T> {
+    pub const fn new(value: T) -> Self {
+        Self {
+            inner: Mutex::new(value),
+        }
+    }
+
+    pub fn lock(&self) -> IrqSpinLockGuard<'_, T> {
+        let daif = daif::save_and_mask();
+        loop {
+            if let Some(guard) = self.inner.try_lock() {
+                return IrqSpinLockGuard {
+                    guard: ManuallyDrop::new(guard),
+                    daif,
+                };
+            }
+            // The holder sends an event once it releases the lock.
+            wait_for_event();
+        }
+    }
+
+    /// Takes the lock if it is free, without spinning.
+    pub fn try_lock(&self) -> Option<IrqSpinLockGuard<'_, T>> {
+        let daif = daif::save_and_mask();
+        match self.inner.try_lock() {
+            Some(guard) => Some(IrqSpinLockGuard {
+                guard: ManuallyDrop::new(guard),
+                daif,
+            }),
+            None => {
+                daif::restore(daif);
+                None
+            }
+        }
+    }
+}
+
+/// Releases the lock and restores the interrupt mask when dropped.
+pub struct IrqSpinLockGuard<'a, T> {
+    guard: ManuallyDrop<MutexGuard<'a, T>>,
+    /// DAIF before the lock was taken.
+    daif: u64,
+}
+
+impl<T> Deref for IrqSpinLockGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T> DerefMut for IrqSpinLockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T> Drop for IrqSpinLockGuard<'_, T> {
+    fn drop(&mut self) {
+        // Interrupts stay masked until the lock has been released.
+        unsafe { ManuallyDrop::drop(&mut self.guard) };
+        send_event();
+        daif::restore(self.daif);
+    }
+}
+
+/// Marks a free [`ReentrantIrqSpinLock`].
+const NO_OWNER: usize = usize::MAX;
+
+/// IRQ-safe spinlock the holding core can take again, for code which recurses
+/// into itself, like walks of the translation tables allocating new tables.
+///
+/// It guards data outside of it, which can't be moved behind a lock.
+pub struct ReentrantIrqSpinLock {
+    owner: AtomicUsize,
+    /// Times the owner has taken the lock, only accessed by the owner.
+    depth: UnsafeCell<usize>,
+}
+
+unsafe impl Sync for ReentrantIrqSpinLock {}
+
+impl Default for ReentrantIrqSpinLock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReentrantIrqSpinLock {
+    pub const fn new() -> Self {
+        Self {
+            owner: AtomicUsize::new(NO_OWNER),
+            depth: UnsafeCell::new(0),
+        }
+    }
+
+    pub fn lock(&self) -> ReentrantIrqSpinLockGuard<'_> {
+        let daif = daif::save_and_mask();
+        let core = current_core();
+
+        if self.owner.load(Ordering::Relaxed) != core {
+            while self
+                .owner
+                .compare_exchange_weak(NO_OWNER, core, Ordering::Acquire, Ordering::Relaxed)
+                .is_err()
+            {
+                wait_for_event();
+            }
+        }
+        unsafe { *self.depth.get() += 1 };
+
+        ReentrantIrqSpinLockGuard { lock: self, daif }
+    }
+}
+
+pub struct ReentrantIrqSpinLockGuard<'a> {
+    lock: &'a ReentrantIrqSpinLock,
+    daif: u64,
+}
+
+impl Drop for ReentrantIrqSpinLockGuard<'_> {
+    fn drop(&mut self) {
+        let depth = unsafe { &mut *self.lock.depth.get() };
+        *depth -= 1;
+        if *depth == 0 {
+            self.lock.owner.store(NO_OWNER, Ordering::Release);
+            send_event();
+        }
+        daif::restore(self.daif);
+    }
+}
+
+/// One instance of `T` per core, indexed by the core number of MPIDR_EL1.
+///
+/// Only the owning core should change its instance, so `T` usually consists of
+/// atomics or cells which are accessed with interrupts masked.
+pub struct PerCpu<T> {
+    values: [T; CORE_COUNT],
+}
+
+impl<T> PerCpu<T> {
+    pub const fn new(values: [T; CORE_COUNT]) -> Self {
+        Self { values }
+    }
+
+    /// Instance of the calling core.
+    pub fn get(&self) -> &T {
+        &self.values[current_core()]
+    }
+
+    pub fn for_core(&self, core: usize) -> &T {
+        &self.values[core]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.values.iter()
+    }
+}
@@ -0,0 +1,208 @@
+use log::debug;
+
+use crate::{
+    application_manager::{
+        current_pid, exit_current,
+        process::{ExitStatus, Signal},
+        schedule, wait, yield_current,
+    },
+    interrupt_handlers::TrapFrame,
+    pi3::mailbox,
+};
+
+pub const SYS_EXIT: u64 = 0;
+pub const SYS_WAIT: u64 = 1;
+pub const SYS_YIELD: u64 = 2;
+pub const SYS_GETPID: u64 = 3;
+pub const SYS_MMAP: u64 = 4;
+pub const SYS_MUNMAP: u64 = 5;
+pub const SYS_BRK: u64 = 6;
+pub const SYS_FORK: u64 = 7;
+pub const SYS_EXEC: u64 = 8;
+pub const SYS_CHANNEL_CREATE: u64 = 9;
+pub const SYS_CHANNEL_CLOSE: u64 = 10;
+pub const SYS_CHANNEL_SEND: u64 = 11;
+pub const SYS_CHANNEL_RECEIVE: u64 = 12;
+pub const SYS_SHM_CREATE: u64 = 13;
+pub const SYS_SHM_OPEN: u64 = 14;
+pub const SYS_SHM_MAP: u64 = 15;
+pub const SYS_SHM_CLOSE: u64 = 16;
+pub const SYS_READ: u64 = 17;
+pub const SYS_WRITE: u64 = 18;
+pub const SYS_CLOSE: u64 = 19;
+pub const SYS_DUP2: u64 = 20;
+pub const SYS_PIPE: u64 = 21;
+pub const SYS_SIGACTION: u64 = 22;
+pub const SYS_SIGRETURN: u64 = 23;
+pub const SYS_KILL: u64 = 24;
+pub const SYS_NANOSLEEP: u64 = 25;
+pub const SYS_SLEEP_UNTIL: u64 = 26;
+pub const SYS_GET_TIME: u64 = 27;
+pub const SYS_CLOCK_GETTIME: u64 = 28;
+pub const SYS_CLOCK_SETTIME: u64 = 29;
+pub const SYS_SCHED_SETAFFINITY: u64 = 30;
+pub const SYS_SCHED_GETAFFINITY: u64 = 31;
+pub const SYS_READ_SOC_TEMP: u64 = 67;
+
+/// Error numbers handed back to EL0.
+///
+/// Values match the Linux AArch64 ABI, syscalls return them negated in `x0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(isize)]
+pub enum Errno {
+    EPERM = 1,
+    ENOENT = 2,
+    ESRCH = 3,
+    EINTR = 4,
+    E2BIG = 7,
+    EBADF = 9,
+    ECHILD = 10,
+    EAGAIN = 11,
+    ENOMEM = 12,
+    EFAULT = 14,
+    EEXIST = 17,
+    EINVAL = 22,
+    EMFILE = 24,
+    EPIPE = 32,
+    ENAMETOOLONG = 36,
+    ENOSYS = 38,
+    EMSGSIZE = 90,
+}
+
+impl Errno {
+    /// Encodes the error as syscall return value.
+    pub fn to_return_value(self) -> usize {
+        (-(self as isize)) as usize
+    }
+}
+
+/// Executes the syscall `x8` with the arguments `x0..x5`.
+///
+/// The result is returned in `x0`, afterwards the scheduler picks the context
+/// to return to.
+pub fn dispatch(frame: &mut TrapFrame) {
+    let result = match frame.x8 {
+        SYS_EXIT => {
+            debug!("Program exited!");
+            exit_current(ExitStatus::Exited(frame.x0 as i32));
+            Ok(0)
+        }
+        SYS_WAIT => match wait(frame, frame.x0 as usize) {
+            Ok(Some(status)) => Ok(status.wait_status()),
+            // Blocked, the status is handed over once the process terminates.
+            Ok(None) => {
+                schedule(frame);
+                return;
+            }
+            Err(errno) => Err(errno),
+        },
+        SYS_YIELD => {
+            frame.x0 = 0;
+            yield_current(frame);
+            return;
+        }
+        SYS_GETPID => Ok(current_pid().unwrap_or(0)),
+        SYS_MMAP => memory::mmap(frame.x0 as usize, frame.x1 as usize, frame.x2, frame.x3),
+        SYS_MUNMAP => memory::munmap(frame.x0 as usize, frame.x1 as usize),
+        SYS_BRK => memory::brk(frame.x0 as usize),
+        SYS_FORK => process::fork(frame),
+        SYS_EXEC => match process::exec(frame, frame.x0 as usize, frame.x1 as usize) {
+            // The frame already holds the context of the new program.
+            Ok(()) => {
+                schedule(frame);
+                return;
+            }
+            Err(errno) => Err(errno),
+        },
+        SYS_CHANNEL_CREATE => ipc::channel_create(),
+        SYS_CHANNEL_CLOSE => ipc::channel_close(frame.x0 as usize),
+        SYS_CHANNEL_SEND => ipc::channel_send(frame.x0 as usize, frame.x1 as usize),
+        SYS_CHANNEL_RECEIVE => {
+            match ipc::channel_receive(frame, frame.x0 as usize, frame.x1 as usize, frame.x2) {
+                Ok(Some(value)) => Ok(value),
+                // Blocked, the syscall is restarted once a message arrives.
+                Ok(None) => {
+                    schedule(frame);
+                    return;
+                }
+                Err(errno) => Err(errno),
+            }
+        }
+        SYS_SHM_CREATE => shared_memory::shm_create(frame.x0 as usize, frame.x1 as usize),
+        SYS_SHM_OPEN => shared_memory::shm_open(frame.x0 as usize),
+        SYS_SHM_MAP => shared_memory::shm_map(frame.x0 as usize, frame.x1),
+        SYS_SHM_CLOSE => shared_memory::shm_close(frame.x0 as usize),
+        SYS_READ | SYS_WRITE => {
+            let (fd, buffer, len) = (frame.x0 as usize, frame.x1 as usize, frame.x2 as usize);
+            let result = if frame.x8 == SYS_READ {
+                files::read(frame, fd, buffer, len)
+            } else {
+                files::write(frame, fd, buffer, len)
+            };
+            match result {
+                Ok(Some(count)) => Ok(count),
+                // Blocked, the syscall is restarted once the file is ready.
+                Ok(None) => {
+                    schedule(frame);
+                    return;
+                }
+                Err(errno) => Err(errno),
+            }
+        }
+        SYS_CLOSE => files::close(frame.x0 as usize),
+        SYS_DUP2 => files::dup2(frame.x0 as usize, frame.x1 as usize),
+        SYS_PIPE => files::pipe(frame.x0 as usize),
+        SYS_SIGACTION => signal::sigaction(frame.x0 as usize, frame.x1 as usize, frame.x2 as usize),
+        SYS_SIGRETURN => {
+            // Without a valid signal frame, the handler has nowhere to return to.
+            if signal::sigreturn(frame).is_err() {
+                exit_current(ExitStatus::Signaled(Signal::SIGSEGV));
+            }
+            schedule(frame);
+            return;
+        }
+        SYS_KILL => signal::kill(frame.x0 as usize, frame.x1 as usize),
+        SYS_NANOSLEEP | SYS_SLEEP_UNTIL => {
+            let result = if frame.x8 == SYS_NANOSLEEP {
+                time::nanosleep(frame, frame.x0)
+            } else {
+                time::sleep_until(frame, frame.x0)
+            };
+            match result {
+                Some(value) => Ok(value),
+                // Blocked, the result is handed over once the process is woken.
+                None => {
+                    schedule(frame);
+                    return;
+                }
+            }
+        }
+        SYS_GET_TIME => Ok(time::get_time() as usize),
+        SYS_CLOCK_GETTIME => time::clock_gettime(frame.x0, frame.x1 as usize),
+        SYS_CLOCK_SETTIME => time::clock_settime(frame.x0, frame.x1 as usize),
+        SYS_SCHED_SETAFFINITY => process::sched_setaffinity(frame.x0 as usize, frame.x1),
+        SYS_SCHED_GETAFFINITY => process::sched_getaffinity(frame.x0 as usize),
+        SYS_READ_SOC_TEMP => {
+            let response = mailbox::read_soc_temp([0]).unwrap();
+            Ok(response[1] as usize)
+        }
+        _ => Err(Errno::ENOSYS),
+    };
+
+    frame.x0 = match result {
+        Ok(value) => value as u64,
+        Err(errno) => errno.to_return_value() as u64,
+    };
+
+    schedule(frame);
+}
+
+pub mod files;
+pub mod ipc;
+pub mod memory;
+pub mod process;
+pub mod shared_memory;
+pub mod signal;
+pub mod time;
+pub mod user;
+pub mod user_access;
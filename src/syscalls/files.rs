@@ -0,0 +1,77 @@
+//! File descriptor syscalls: `read`, `write`, `close`, `dup2` and `pipe`.
+
+use crate::{
+    aarch64::mmu::VirtAddr,
+    application_manager,
+    interrupt_handlers::TrapFrame,
+    syscalls::{
+        user_access::{copy_from_user, copy_to_user, prefault_for_write, write_to_user},
+        Errno,
+    },
+};
+
+/// Upper bound of bytes transferred by a single `read` or `write`.
+const IO_CHUNK: usize = 512;
+
+/// Reads up to `len` bytes from `fd` into `buffer` and returns their number.
+///
+/// Returns `None` if the process has been blocked until data is available,
+/// the syscall is then restarted.
+pub fn read(
+    frame: &TrapFrame,
+    fd: usize,
+    buffer: VirtAddr,
+    len: usize,
+) -> Result<Option<usize>, Errno> {
+    let mut data = [0; IO_CHUNK];
+    let data = &mut data[..len.min(IO_CHUNK)];
+
+    // Fault the buffer in up front, read data can't be put back.
+    prefault_for_write(buffer, data.len())?;
+
+    let Some(count) = application_manager::read(frame, fd, data)? else {
+        return Ok(None);
+    };
+    copy_to_user(buffer, &data[..count])?;
+    Ok(Some(count))
+}
+
+/// Writes up to `len` bytes from `buffer` to `fd` and returns their number.
+///
+/// Returns `None` if the process has been blocked until the pipe has space,
+/// the syscall is then restarted.
+pub fn write(
+    frame: &TrapFrame,
+    fd: usize,
+    buffer: VirtAddr,
+    len: usize,
+) -> Result<Option<usize>, Errno> {
+    let mut data = [0; IO_CHUNK];
+    let data = &mut data[..len.min(IO_CHUNK)];
+    copy_from_user(data, buffer)?;
+
+    application_manager::write(frame, fd, data)
+}
+
+pub fn close(fd: usize) -> Result<usize, Errno> {
+    application_manager::close(fd)?;
+    Ok(0)
+}
+
+pub fn dup2(old_fd: usize, new_fd: usize) -> Result<usize, Errno> {
+    application_manager::dup2(old_fd, new_fd)
+}
+
+/// Creates a pipe and stores the descriptors of its read and write end as
+/// `[i32; 2]` at `fds`.
+pub fn pipe(fds: VirtAddr) -> Result<usize, Errno> {
+    write_to_user(fds, &[-1i32; 2])?;
+
+    let (reader, writer) = application_manager::pipe()?;
+    if let Err(errno) = write_to_user(fds, &[reader as i32, writer as i32]) {
+        let _ = application_manager::close(reader);
+        let _ = application_manager::close(writer);
+        return Err(errno);
+    }
+    Ok(0)
+}
@@ -0,0 +1,94 @@
+//! Message passing syscalls, see [`crate::application_manager::ipc`].
+
+use crate::{
+    aarch64::mmu::VirtAddr,
+    application_manager::{self, ipc::MESSAGE_SIZE},
+    interrupt_handlers::TrapFrame,
+    syscalls::{
+        memory::{page_flags, PROT_READ, PROT_WRITE},
+        user_access::{prefault_for_write, read_from_user, write_to_user},
+        Errno,
+    },
+};
+
+/// Makes `channel_receive` fail with `EAGAIN` instead of blocking.
+pub const RECEIVE_NONBLOCK: u64 = 1;
+
+/// Message as exchanged with EL0.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct UserMessage {
+    /// Bytes of `data` in use.
+    pub len: u64,
+    /// Address of an mmap page transferred with the message, `0` if there is none.
+    ///
+    /// The page is unmapped from the sender and mapped read-write into the receiver.
+    pub page: u64,
+    pub data: [u8; MESSAGE_SIZE],
+}
+
+impl Default for UserMessage {
+    fn default() -> Self {
+        Self {
+            len: 0,
+            page: 0,
+            data: [0; MESSAGE_SIZE],
+        }
+    }
+}
+
+pub fn channel_create() -> Result<usize, Errno> {
+    application_manager::create_channel()
+}
+
+pub fn channel_close(id: usize) -> Result<usize, Errno> {
+    application_manager::close_channel(id)?;
+    Ok(0)
+}
+
+/// Queues the message at `message` on the channel `id`, without blocking.
+pub fn channel_send(id: usize, message: VirtAddr) -> Result<usize, Errno> {
+    let message: UserMessage = read_from_user(message)?;
+    let len = usize::try_from(message.len).map_err(|_| Errno::EMSGSIZE)?;
+    let data = message.data.get(..len).ok_or(Errno::EMSGSIZE)?;
+    let page = (message.page != 0).then_some(message.page as VirtAddr);
+
+    application_manager::send(id, data, page)?;
+    Ok(0)
+}
+
+/// Stores the oldest message of the channel `id` at `message`.
+///
+/// Returns `None` if the process has been blocked until a message arrives,
+/// the syscall is then restarted.
+pub fn channel_receive(
+    frame: &TrapFrame,
+    id: usize,
+    message: VirtAddr,
+    flags: u64,
+) -> Result<Option<usize>, Errno> {
+    if flags & !RECEIVE_NONBLOCK != 0 {
+        return Err(Errno::EINVAL);
+    }
+
+    // Fault the buffer in up front, a message can't be put back once taken.
+    prefault_for_write(message, size_of::<UserMessage>())?;
+
+    let Some((received, page)) = application_manager::receive(
+        frame,
+        id,
+        flags & RECEIVE_NONBLOCK != 0,
+        page_flags(PROT_READ | PROT_WRITE),
+    )?
+    else {
+        return Ok(None);
+    };
+
+    let user_message = UserMessage {
+        len: received.len as u64,
+        page: page.unwrap_or(0) as u64,
+        data: received.data,
+    };
+    write_to_user(message, &user_message)?;
+    Ok(Some(0))
+}
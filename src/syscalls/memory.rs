@@ -0,0 +1,95 @@
+//! Memory management syscalls: `mmap`, `munmap` and `brk`.
+
+use nova_error::NovaError;
+
+use crate::{
+    aarch64::mmu::{
+        VirtAddr, EL0_ACCESSIBLE, GRANULARITY, NORMAL_MEM, PXN, READ_ONLY, UXN, WRITABLE,
+    },
+    application_manager::with_current_address_space,
+    syscalls::Errno,
+};
+
+pub const PROT_NONE: u64 = 0;
+pub const PROT_READ: u64 = 1 << 0;
+pub const PROT_WRITE: u64 = 1 << 1;
+pub const PROT_EXEC: u64 = 1 << 2;
+
+pub const MAP_SHARED: u64 = 0x01;
+pub const MAP_PRIVATE: u64 = 0x02;
+pub const MAP_FIXED: u64 = 0x10;
+pub const MAP_ANONYMOUS: u64 = 0x20;
+
+/// Creates an anonymous private mapping of `length` bytes, rounded up to pages.
+pub fn mmap(address: VirtAddr, length: usize, prot: u64, flags: u64) -> Result<usize, Errno> {
+    if length == 0
+        || prot & !(PROT_READ | PROT_WRITE | PROT_EXEC) != 0
+        || flags & (MAP_ANONYMOUS | MAP_PRIVATE) != MAP_ANONYMOUS | MAP_PRIVATE
+        || flags & MAP_SHARED != 0
+    {
+        return Err(Errno::EINVAL);
+    }
+
+    let size = length
+        .checked_next_multiple_of(GRANULARITY)
+        .ok_or(Errno::ENOMEM)?;
+
+    let fixed_address = if flags & MAP_FIXED != 0 {
+        if !address.is_multiple_of(GRANULARITY) || address.checked_add(size).is_none() {
+            return Err(Errno::EINVAL);
+        }
+        Some(address)
+    } else {
+        None
+    };
+
+    with_current_address_space(|space| space.mmap(fixed_address, size, page_flags(prot)))
+        .ok_or(Errno::EINVAL)?
+        .map_err(|err| match err {
+            NovaError::OutOfMeomory => Errno::ENOMEM,
+            _ => Errno::EINVAL,
+        })
+}
+
+/// Removes mappings created by `mmap` within `length` bytes starting at `address`.
+pub fn munmap(address: VirtAddr, length: usize) -> Result<usize, Errno> {
+    if !address.is_multiple_of(GRANULARITY) || length == 0 {
+        return Err(Errno::EINVAL);
+    }
+
+    let size = length
+        .checked_next_multiple_of(GRANULARITY)
+        .ok_or(Errno::EINVAL)?;
+    address.checked_add(size).ok_or(Errno::EINVAL)?;
+
+    with_current_address_space(|space| space.munmap(address, size)).ok_or(Errno::EINVAL)?;
+    Ok(0)
+}
+
+/// Moves the program break to `address` and returns the new break.
+///
+/// Like on Linux, the current break is returned unchanged if the break can't be
+/// moved, `brk(0)` queries it.
+pub fn brk(address: VirtAddr) -> Result<usize, Errno> {
+    with_current_address_space(|space| space.brk(address, page_flags(PROT_READ | PROT_WRITE)))
+        .ok_or(Errno::EINVAL)
+}
+
+/// Translation table flags of an EL0 mapping with the protection `prot`.
+pub fn page_flags(prot: u64) -> u64 {
+    let mut flags = NORMAL_MEM | PXN;
+
+    if prot != PROT_NONE {
+        flags |= EL0_ACCESSIBLE;
+    }
+    flags |= if prot & PROT_WRITE != 0 {
+        WRITABLE
+    } else {
+        READ_ONLY
+    };
+    if prot & PROT_EXEC == 0 {
+        flags |= UXN;
+    }
+
+    flags
+}
@@ -0,0 +1,88 @@
+//! Process management syscalls: `fork`, `exec` and the CPU affinity.
+
+use alloc::{string::String, vec::Vec};
+use nova_error::NovaError;
+
+use crate::{
+    aarch64::mmu::VirtAddr,
+    application_manager::{self, process::Pid},
+    interrupt_handlers::TrapFrame,
+    smp::CpuMask,
+    syscalls::{
+        user_access::{read_from_user, strncpy_from_user},
+        Errno,
+    },
+};
+
+/// Upper bound of arguments passed to `exec`, including the program name.
+const MAX_ARGS: usize = 32;
+
+/// Upper bound of the length of a single argument, including the NUL.
+const MAX_ARG_LEN: usize = 256;
+
+/// Duplicates the calling process and returns the PID of the child.
+pub fn fork(frame: &TrapFrame) -> Result<usize, Errno> {
+    application_manager::fork(frame).map_err(errno)
+}
+
+/// Replaces the program of the calling process by the app `app_id`, started
+/// with the NULL-terminated argument vector at `argv`.
+///
+/// On success `frame` holds the initial context of the new program.
+pub fn exec(frame: &mut TrapFrame, app_id: usize, argv: VirtAddr) -> Result<(), Errno> {
+    // User memory is read before the app manager is locked, as faulting pages
+    // in needs the lock as well.
+    let args = read_args(argv)?;
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    application_manager::exec(frame, app_id, &args).map_err(errno)
+}
+
+/// Copies the NULL-terminated array of strings at `argv` from EL0.
+fn read_args(argv: VirtAddr) -> Result<Vec<String>, Errno> {
+    let mut args = Vec::new();
+    if argv == 0 {
+        return Ok(args);
+    }
+
+    loop {
+        let slot = argv
+            .checked_add(args.len() * size_of::<VirtAddr>())
+            .ok_or(Errno::EFAULT)?;
+        let pointer: VirtAddr = read_from_user(slot)?;
+        if pointer == 0 {
+            return Ok(args);
+        }
+        if args.len() == MAX_ARGS {
+            return Err(Errno::E2BIG);
+        }
+
+        let mut buffer = [0; MAX_ARG_LEN];
+        let len = strncpy_from_user(&mut buffer, pointer)?;
+        if len == MAX_ARG_LEN {
+            return Err(Errno::E2BIG);
+        }
+
+        let arg = core::str::from_utf8(&buffer[..len]).map_err(|_| Errno::EINVAL)?;
+        args.push(String::from(arg));
+    }
+}
+
+/// Restricts the process `pid`, or the calling one for `0`, to the cores in `mask`.
+///
+/// Fails with `EINVAL` if none of these cores is running.
+pub fn sched_setaffinity(pid: Pid, mask: CpuMask) -> Result<usize, Errno> {
+    application_manager::set_affinity(pid, mask).map(|()| 0)
+}
+
+/// Returns the mask of cores the process `pid`, or the calling one for `0`, may run on.
+pub fn sched_getaffinity(pid: Pid) -> Result<usize, Errno> {
+    application_manager::affinity(pid).map(|mask| mask as usize)
+}
+
+fn errno(err: NovaError) -> Errno {
+    match err {
+        NovaError::OutOfMeomory => Errno::ENOMEM,
+        _ => Errno::EINVAL,
+    }
+}
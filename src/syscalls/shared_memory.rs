@@ -0,0 +1,58 @@
+//! Shared memory syscalls, see [`crate::application_manager::shared_memory`].
+
+use alloc::string::String;
+
+use crate::{
+    aarch64::mmu::VirtAddr,
+    application_manager,
+    syscalls::{
+        memory::{page_flags, PROT_EXEC, PROT_READ, PROT_WRITE},
+        user_access::strncpy_from_user,
+        Errno,
+    },
+};
+
+/// Upper bound of the length of an object name, including the NUL.
+const NAME_MAX: usize = 64;
+
+/// Creates a shared memory object of `size` bytes and returns its id.
+///
+/// The object can be opened by other processes, if `name` points to a name.
+pub fn shm_create(size: usize, name: VirtAddr) -> Result<usize, Errno> {
+    let name = if name == 0 {
+        None
+    } else {
+        Some(read_name(name)?)
+    };
+    application_manager::create_shared_memory(name, size)
+}
+
+pub fn shm_open(name: VirtAddr) -> Result<usize, Errno> {
+    application_manager::open_shared_memory(&read_name(name)?)
+}
+
+/// Maps the object `id` with the protection `prot` and returns its address.
+///
+/// The mapping is removed with `munmap`.
+pub fn shm_map(id: usize, prot: u64) -> Result<usize, Errno> {
+    if prot & !(PROT_READ | PROT_WRITE | PROT_EXEC) != 0 {
+        return Err(Errno::EINVAL);
+    }
+    application_manager::map_shared_memory(id, page_flags(prot))
+}
+
+pub fn shm_close(id: usize) -> Result<usize, Errno> {
+    application_manager::close_shared_memory(id)?;
+    Ok(0)
+}
+
+fn read_name(address: VirtAddr) -> Result<String, Errno> {
+    let mut buffer = [0; NAME_MAX];
+    let len = strncpy_from_user(&mut buffer, address)?;
+    if len == NAME_MAX {
+        return Err(Errno::ENAMETOOLONG);
+    }
+
+    let name = core::str::from_utf8(&buffer[..len]).map_err(|_| Errno::EINVAL)?;
+    Ok(String::from(name))
+}
@@ -0,0 +1,30 @@
+//! Signal syscalls: `sigaction`, `sigreturn` and `kill`.
+
+use crate::{
+    aarch64::mmu::VirtAddr,
+    application_manager::{self, process::Signal, signal::SignalFrame},
+    interrupt_handlers::TrapFrame,
+    syscalls::{user_access::read_from_user, Errno},
+};
+
+/// Registers `handler` for the signal `number` and returns the previous handler.
+///
+/// `restorer` is entered once a handler returns and has to call `sigreturn`.
+/// A handler of `0` restores the default action, terminating the process.
+pub fn sigaction(number: usize, handler: VirtAddr, restorer: VirtAddr) -> Result<usize, Errno> {
+    let signal = Signal::from_number(number).ok_or(Errno::EINVAL)?;
+    application_manager::sigaction(signal, handler, restorer)
+}
+
+/// Restores the context saved before the current signal handler was entered.
+pub fn sigreturn(frame: &mut TrapFrame) -> Result<(), Errno> {
+    let signal_frame: SignalFrame = read_from_user(frame.sp_el0 as VirtAddr)?;
+    application_manager::sigreturn(frame, &signal_frame)
+}
+
+/// Sends the signal `number` to the process `pid`.
+pub fn kill(pid: usize, number: usize) -> Result<usize, Errno> {
+    let signal = Signal::from_number(number).ok_or(Errno::EINVAL)?;
+    application_manager::send_signal(pid, signal)?;
+    Ok(0)
+}
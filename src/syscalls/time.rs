@@ -0,0 +1,93 @@
+//! Clock syscalls: `get_time`, `clock_gettime`, `clock_settime`, `nanosleep`
+//! and `sleep_until`.
+//!
+//! Times are nanoseconds of the monotonic clock, see [`Instant`]. Its
+//! resolution is a microsecond, sleeps are rounded up to whole microseconds.
+
+use crate::{
+    aarch64::mmu::VirtAddr,
+    application_manager,
+    interrupt_handlers::TrapFrame,
+    syscalls::{
+        user_access::{read_from_user, write_to_user},
+        Errno,
+    },
+    time::{self, Duration, Instant},
+};
+
+/// Wall clock, seconds since the Unix epoch.
+pub const CLOCK_REALTIME: u64 = 0;
+/// Monotonic clock, seconds since boot.
+pub const CLOCK_MONOTONIC: u64 = 1;
+
+/// Point in time as exchanged with EL0, like `struct timespec`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Timespec {
+    pub tv_sec: i64,
+    /// Nanoseconds within the second, `0..1_000_000_000`.
+    pub tv_nsec: i64,
+}
+
+impl Timespec {
+    pub fn from_duration(duration: Duration) -> Self {
+        Self {
+            tv_sec: duration.as_secs() as i64,
+            tv_nsec: duration.subsec_nanos() as i64,
+        }
+    }
+
+    /// `None` for negative times and out of range nanoseconds.
+    pub fn to_duration(self) -> Option<Duration> {
+        let seconds = u64::try_from(self.tv_sec).ok()?;
+        let nanos = u32::try_from(self.tv_nsec)
+            .ok()
+            .filter(|nanos| *nanos < 1_000_000_000)?;
+        Some(Duration::new(seconds, nanos))
+    }
+}
+
+/// Nanoseconds since boot.
+pub fn get_time() -> u64 {
+    Instant::now().since_boot().as_nanos() as u64
+}
+
+/// Writes the current time of the clock `clock_id` to `tp`.
+pub fn clock_gettime(clock_id: u64, tp: VirtAddr) -> Result<usize, Errno> {
+    let time = match clock_id {
+        CLOCK_REALTIME => time::wall_clock(),
+        CLOCK_MONOTONIC => Instant::now().since_boot(),
+        _ => return Err(Errno::EINVAL),
+    };
+    write_to_user(tp, &Timespec::from_duration(time))?;
+    Ok(0)
+}
+
+/// Sets the clock `clock_id` to the time at `tp`, only the wall clock can be set.
+pub fn clock_settime(clock_id: u64, tp: VirtAddr) -> Result<usize, Errno> {
+    if clock_id != CLOCK_REALTIME {
+        return Err(Errno::EINVAL);
+    }
+    let time: Timespec = read_from_user(tp)?;
+    time::set_wall_clock(time.to_duration().ok_or(Errno::EINVAL)?);
+    Ok(0)
+}
+
+/// Blocks the calling process for `duration_ns` nanoseconds.
+///
+/// Returns `None` if the process has been blocked, `0` is returned on wakeup.
+pub fn nanosleep(frame: &TrapFrame, duration_ns: u64) -> Option<usize> {
+    let deadline = Instant::after(Duration::from_nanos(duration_ns));
+    sleep_until(frame, deadline.as_micros().saturating_mul(1_000))
+}
+
+/// Blocks the calling process until the system timer reaches `deadline_ns`.
+///
+/// Returns `None` if the process has been blocked, `0` is returned on wakeup.
+pub fn sleep_until(frame: &TrapFrame, deadline_ns: u64) -> Option<usize> {
+    if application_manager::sleep_until(frame, deadline_ns.div_ceil(1_000)) {
+        None
+    } else {
+        Some(0)
+    }
+}
@@ -0,0 +1,258 @@
+//! Syscall wrappers for code running in EL0.
+
+use core::arch::asm;
+
+use crate::{
+    application_manager::process::Pid,
+    syscalls::{
+        ipc::UserMessage, time::Timespec, SYS_BRK, SYS_CHANNEL_CLOSE, SYS_CHANNEL_CREATE,
+        SYS_CHANNEL_RECEIVE, SYS_CHANNEL_SEND, SYS_CLOCK_GETTIME, SYS_CLOCK_SETTIME, SYS_CLOSE,
+        SYS_DUP2, SYS_EXEC, SYS_EXIT, SYS_FORK, SYS_GETPID, SYS_GET_TIME, SYS_KILL, SYS_MMAP,
+        SYS_MUNMAP, SYS_NANOSLEEP, SYS_PIPE, SYS_READ, SYS_READ_SOC_TEMP, SYS_SCHED_GETAFFINITY,
+        SYS_SCHED_SETAFFINITY, SYS_SHM_CLOSE, SYS_SHM_CREATE, SYS_SHM_MAP, SYS_SHM_OPEN,
+        SYS_SIGACTION, SYS_SIGRETURN, SYS_SLEEP_UNTIL, SYS_WAIT, SYS_WRITE, SYS_YIELD,
+    },
+};
+
+/// Raw syscall `nr` with up to six arguments in `x0..x5`.
+pub fn syscall(nr: u64, args: [u64; 6]) -> u64 {
+    let ret: u64;
+
+    unsafe {
+        asm!(
+            "svc #0",
+            in("x8") nr,
+            inlateout("x0") args[0] => ret,
+            in("x1") args[1],
+            in("x2") args[2],
+            in("x3") args[3],
+            in("x4") args[4],
+            in("x5") args[5],
+        );
+    }
+
+    ret
+}
+
+/// Terminates the calling process with `code`.
+pub fn exit(code: i32) -> ! {
+    syscall(SYS_EXIT, [code as u64, 0, 0, 0, 0, 0]);
+    unreachable!()
+}
+
+/// Blocks until `pid` terminates and returns its wait status.
+pub fn wait(pid: Pid) -> i64 {
+    syscall(SYS_WAIT, [pid as u64, 0, 0, 0, 0, 0]) as i64
+}
+
+/// Hands the CPU to the next ready process.
+pub fn yield_now() {
+    syscall(SYS_YIELD, [0; 6]);
+}
+
+pub fn getpid() -> Pid {
+    syscall(SYS_GETPID, [0; 6]) as Pid
+}
+
+/// Maps `length` bytes of anonymous memory, see [`crate::syscalls::memory`] for
+/// `prot` and `flags`.
+///
+/// Returns the address of the mapping or a negative error number.
+pub fn mmap(address: usize, length: usize, prot: u64, flags: u64) -> i64 {
+    syscall(
+        SYS_MMAP,
+        [address as u64, length as u64, prot, flags, u64::MAX, 0],
+    ) as i64
+}
+
+pub fn munmap(address: usize, length: usize) -> i64 {
+    syscall(SYS_MUNMAP, [address as u64, length as u64, 0, 0, 0, 0]) as i64
+}
+
+/// Moves the program break to `address` and returns the resulting break.
+pub fn brk(address: usize) -> usize {
+    syscall(SYS_BRK, [address as u64, 0, 0, 0, 0, 0]) as usize
+}
+
+/// Duplicates the calling process.
+///
+/// Returns the PID of the child to the parent, `0` to the child or a negative
+/// error number.
+pub fn fork() -> i64 {
+    syscall(SYS_FORK, [0; 6]) as i64
+}
+
+/// Replaces the program of the calling process by the app `app_id`.
+///
+/// `argv` points to a NULL-terminated array of NUL-terminated strings. Only
+/// returns on failure, with a negative error number.
+pub fn exec(app_id: usize, argv: *const *const u8) -> i64 {
+    syscall(SYS_EXEC, [app_id as u64, argv as u64, 0, 0, 0, 0]) as i64
+}
+
+/// Creates a channel and returns its id or a negative error number.
+pub fn channel_create() -> i64 {
+    syscall(SYS_CHANNEL_CREATE, [0; 6]) as i64
+}
+
+pub fn channel_close(id: usize) -> i64 {
+    syscall(SYS_CHANNEL_CLOSE, [id as u64, 0, 0, 0, 0, 0]) as i64
+}
+
+/// Queues `message` on the channel `id`, fails with `EAGAIN` if the channel is full.
+pub fn channel_send(id: usize, message: &UserMessage) -> i64 {
+    syscall(
+        SYS_CHANNEL_SEND,
+        [id as u64, message as *const _ as u64, 0, 0, 0, 0],
+    ) as i64
+}
+
+/// Receives the oldest message of the channel `id` into `message`, see
+/// [`crate::syscalls::ipc::RECEIVE_NONBLOCK`] for `flags`.
+pub fn channel_receive(id: usize, message: &mut UserMessage, flags: u64) -> i64 {
+    syscall(
+        SYS_CHANNEL_RECEIVE,
+        [id as u64, message as *mut _ as u64, flags, 0, 0, 0],
+    ) as i64
+}
+
+/// Creates a shared memory object of `size` bytes and returns its id.
+///
+/// `name` is either null or points to a NUL-terminated name, under which other
+/// processes can open the object.
+pub fn shm_create(size: usize, name: *const u8) -> i64 {
+    syscall(SYS_SHM_CREATE, [size as u64, name as u64, 0, 0, 0, 0]) as i64
+}
+
+/// Returns the id of the shared memory object called `name`.
+pub fn shm_open(name: *const u8) -> i64 {
+    syscall(SYS_SHM_OPEN, [name as u64, 0, 0, 0, 0, 0]) as i64
+}
+
+/// Maps the shared memory object `id` and returns its address, see
+/// [`crate::syscalls::memory`] for `prot`.
+pub fn shm_map(id: usize, prot: u64) -> i64 {
+    syscall(SYS_SHM_MAP, [id as u64, prot, 0, 0, 0, 0]) as i64
+}
+
+pub fn shm_close(id: usize) -> i64 {
+    syscall(SYS_SHM_CLOSE, [id as u64, 0, 0, 0, 0, 0]) as i64
+}
+
+/// Reads up to `buffer.len()` bytes from `fd` and returns their number, `0` at
+/// the end of file or a negative error number.
+pub fn read(fd: usize, buffer: &mut [u8]) -> i64 {
+    syscall(
+        SYS_READ,
+        [
+            fd as u64,
+            buffer.as_mut_ptr() as u64,
+            buffer.len() as u64,
+            0,
+            0,
+            0,
+        ],
+    ) as i64
+}
+
+/// Writes up to `data.len()` bytes to `fd` and returns their number or a
+/// negative error number.
+pub fn write(fd: usize, data: &[u8]) -> i64 {
+    syscall(
+        SYS_WRITE,
+        [fd as u64, data.as_ptr() as u64, data.len() as u64, 0, 0, 0],
+    ) as i64
+}
+
+pub fn close(fd: usize) -> i64 {
+    syscall(SYS_CLOSE, [fd as u64, 0, 0, 0, 0, 0]) as i64
+}
+
+/// Makes `new_fd` refer to the file of `old_fd`.
+pub fn dup2(old_fd: usize, new_fd: usize) -> i64 {
+    syscall(SYS_DUP2, [old_fd as u64, new_fd as u64, 0, 0, 0, 0]) as i64
+}
+
+/// Creates a pipe, `fds` receives the descriptors of its read and write end.
+pub fn pipe(fds: &mut [i32; 2]) -> i64 {
+    syscall(SYS_PIPE, [fds.as_mut_ptr() as u64, 0, 0, 0, 0, 0]) as i64
+}
+
+/// Registers `handler` for the signal `number`, `None` restores the default action.
+///
+/// The handler gets the signal number and must return normally, afterwards the
+/// interrupted code continues. Returns the previous handler or a negative error number.
+pub fn signal(number: usize, handler: Option<extern "C" fn(usize)>) -> i64 {
+    let handler = handler.map_or(0, |handler| handler as usize);
+    syscall(
+        SYS_SIGACTION,
+        [
+            number as u64,
+            handler as u64,
+            signal_restorer as *const () as usize as u64,
+            0,
+            0,
+            0,
+        ],
+    ) as i64
+}
+
+/// Entered once a signal handler returns, with the stack pointer at the signal frame.
+extern "C" fn signal_restorer() -> ! {
+    syscall(SYS_SIGRETURN, [0; 6]);
+    unreachable!()
+}
+
+/// Sends the signal `number` to the process `pid`.
+pub fn kill(pid: Pid, number: usize) -> i64 {
+    syscall(SYS_KILL, [pid as u64, number as u64, 0, 0, 0, 0]) as i64
+}
+
+/// Blocks for `duration_ns` nanoseconds, other processes run in the meantime.
+///
+/// Returns `0` or a negative error number, if interrupted by a signal.
+pub fn nanosleep(duration_ns: u64) -> i64 {
+    syscall(SYS_NANOSLEEP, [duration_ns, 0, 0, 0, 0, 0]) as i64
+}
+
+/// Blocks until the system timer reaches `deadline_ns` nanoseconds.
+pub fn sleep_until(deadline_ns: u64) -> i64 {
+    syscall(SYS_SLEEP_UNTIL, [deadline_ns, 0, 0, 0, 0, 0]) as i64
+}
+
+/// Nanoseconds since boot, read from the monotonic clock.
+pub fn get_time() -> u64 {
+    syscall(SYS_GET_TIME, [0; 6])
+}
+
+/// Reads the clock `clock_id`, `CLOCK_REALTIME` or `CLOCK_MONOTONIC`, into `time`.
+pub fn clock_gettime(clock_id: u64, time: &mut Timespec) -> i64 {
+    syscall(
+        SYS_CLOCK_GETTIME,
+        [clock_id, time as *mut _ as u64, 0, 0, 0, 0],
+    ) as i64
+}
+
+/// Sets the wall clock, `clock_id` must be `CLOCK_REALTIME`.
+pub fn clock_settime(clock_id: u64, time: &Timespec) -> i64 {
+    syscall(
+        SYS_CLOCK_SETTIME,
+        [clock_id, time as *const _ as u64, 0, 0, 0, 0],
+    ) as i64
+}
+
+/// Restricts the process `pid`, or the calling one for `0`, to the cores in
+/// `mask`, bit `n` standing for core `n`.
+pub fn sched_setaffinity(pid: Pid, mask: u64) -> i64 {
+    syscall(SYS_SCHED_SETAFFINITY, [pid as u64, mask, 0, 0, 0, 0]) as i64
+}
+
+/// Returns the mask of cores the process `pid`, or the calling one for `0`,
+/// may run on, or a negative error number.
+pub fn sched_getaffinity(pid: Pid) -> i64 {
+    syscall(SYS_SCHED_GETAFFINITY, [pid as u64, 0, 0, 0, 0, 0]) as i64
+}
+
+pub fn read_soc_temp() -> u64 {
+    syscall(SYS_READ_SOC_TEMP, [0; 6])
+}
@@ -0,0 +1,247 @@
+use core::{arch::asm, mem::MaybeUninit};
+
+use crate::{
+    aarch64::{
+        mmu::{VirtAddr, GRANULARITY, KERNEL_VIRTUAL_MEM_SPACE},
+        registers::{read_id_aa64mmfr1_el1, read_par_el1},
+    },
+    application_manager::{address_space::FaultAccess, handle_page_fault},
+    syscalls::Errno,
+};
+
+/// First address above the TTBR0 (EL0) half of the virtual address space.
+const USER_SPACE_END: VirtAddr = !KERNEL_VIRTUAL_MEM_SPACE + 1;
+
+/// PAR_EL1.F, set if the address translation failed.
+const PAR_EL1_FAULT: u64 = 1;
+
+/// SCTLR_EL1.SPAN, RES1 on cores without PAN.
+const SCTLR_EL1_SPAN: u64 = 1 << 23;
+
+// MSR PAN, #imm — encoded by hand, as the baseline ARMv8.0 target doesn't know PAN.
+const MSR_PAN_0: u32 = 0xD500_409F;
+const MSR_PAN_1: u32 = 0xD500_419F;
+
+extern "C" {
+    fn __arch_copy_from_user(destination: *mut u8, source: VirtAddr, len: usize) -> usize;
+    fn __arch_copy_to_user(destination: VirtAddr, source: *const u8, len: usize) -> usize;
+    fn __arch_strncpy_from_user(destination: *mut u8, source: VirtAddr, len: usize) -> isize;
+
+    static __ex_table_start: ExceptionTableEntry;
+    static __ex_table_end: ExceptionTableEntry;
+}
+
+/// Entry of the `__ex_table` section emitted by `user_access.S`.
+#[repr(C)]
+struct ExceptionTableEntry {
+    instruction: usize,
+    fixup: usize,
+}
+
+#[derive(Clone, Copy)]
+enum Access {
+    Read,
+    Write,
+}
+
+/// Keeps PAN lifted for as long as user memory is accessed.
+struct UserAccessWindow {
+    restore_pan: bool,
+}
+
+impl UserAccessWindow {
+    fn open() -> Self {
+        let restore_pan = pan_supported() && pan_enabled();
+        if restore_pan {
+            set_pan(false);
+        }
+        Self { restore_pan }
+    }
+}
+
+impl Drop for UserAccessWindow {
+    fn drop(&mut self) {
+        if self.restore_pan {
+            set_pan(true);
+        }
+    }
+}
+
+/// Copies `destination.len()` bytes from the EL0 address `source`.
+pub fn copy_from_user(destination: &mut [u8], source: VirtAddr) -> Result<(), Errno> {
+    check_user_range(source, destination.len(), Access::Read)?;
+
+    let _window = UserAccessWindow::open();
+    let remaining =
+        unsafe { __arch_copy_from_user(destination.as_mut_ptr(), source, destination.len()) };
+
+    if remaining != 0 {
+        return Err(Errno::EFAULT);
+    }
+    Ok(())
+}
+
+/// Copies `source` to the EL0 address `destination`.
+pub fn copy_to_user(destination: VirtAddr, source: &[u8]) -> Result<(), Errno> {
+    check_user_range(destination, source.len(), Access::Write)?;
+
+    let _window = UserAccessWindow::open();
+    let remaining = unsafe { __arch_copy_to_user(destination, source.as_ptr(), source.len()) };
+
+    if remaining != 0 {
+        return Err(Errno::EFAULT);
+    }
+    Ok(())
+}
+
+/// Faults in the pages of `len` bytes at the EL0 address `destination` for
+/// writing, without writing to them.
+pub fn prefault_for_write(destination: VirtAddr, len: usize) -> Result<(), Errno> {
+    check_user_range(destination, len, Access::Write)
+}
+
+/// Copies a NUL-terminated string from the EL0 address `source`.
+///
+/// Returns the length of the string without the NUL. If no NUL is found within
+/// `destination.len()` bytes, the string is truncated and `destination.len()` is returned.
+pub fn strncpy_from_user(destination: &mut [u8], source: VirtAddr) -> Result<usize, Errno> {
+    let mut copied = 0;
+
+    // Pages are checked one by one, as the string may end before an unmapped page.
+    while copied < destination.len() {
+        let address = source.checked_add(copied).ok_or(Errno::EFAULT)?;
+        let page_remaining = GRANULARITY - (address % GRANULARITY);
+        let chunk = page_remaining.min(destination.len() - copied);
+
+        check_user_range(address, chunk, Access::Read)?;
+
+        let _window = UserAccessWindow::open();
+        let len =
+            unsafe { __arch_strncpy_from_user(destination[copied..].as_mut_ptr(), address, chunk) };
+
+        if len < 0 {
+            return Err(Errno::EFAULT);
+        }
+
+        copied += len as usize;
+        if (len as usize) < chunk {
+            return Ok(copied);
+        }
+    }
+
+    Ok(copied)
+}
+
+/// Reads a plain value of type `T` from the EL0 address `source`.
+pub fn read_from_user<T: Copy>(source: VirtAddr) -> Result<T, Errno> {
+    let mut value = MaybeUninit::<T>::uninit();
+    let bytes =
+        unsafe { core::slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, size_of::<T>()) };
+    copy_from_user(bytes, source)?;
+    Ok(unsafe { value.assume_init() })
+}
+
+/// Writes a plain value of type `T` to the EL0 address `destination`.
+pub fn write_to_user<T: Copy>(destination: VirtAddr, value: &T) -> Result<(), Errno> {
+    let bytes =
+        unsafe { core::slice::from_raw_parts(value as *const T as *const u8, size_of::<T>()) };
+    copy_to_user(destination, bytes)
+}
+
+/// Verifies that EL0 itself may access every page of the range, using the
+/// currently active TTBR0 table.
+///
+/// Pages which aren't backed yet are faulted in, like on an access from EL0.
+fn check_user_range(address: VirtAddr, len: usize, access: Access) -> Result<(), Errno> {
+    if len == 0 {
+        return Ok(());
+    }
+
+    let end = address.checked_add(len).ok_or(Errno::EFAULT)?;
+    if end > USER_SPACE_END {
+        return Err(Errno::EFAULT);
+    }
+
+    let mut page = address & !(GRANULARITY - 1);
+    while page < end {
+        if !el0_can_access(page, access) {
+            let fault_access = match access {
+                Access::Read => FaultAccess::Read,
+                Access::Write => FaultAccess::Write,
+            };
+            handle_page_fault(page, fault_access).map_err(|_| Errno::EFAULT)?;
+        }
+        page += GRANULARITY;
+    }
+    Ok(())
+}
+
+/// Runs a stage 1 EL0 address translation, which applies the EL0 permissions
+/// of the mapping.
+fn el0_can_access(address: VirtAddr, access: Access) -> bool {
+    unsafe {
+        match access {
+            Access::Read => asm!("at s1e0r, {}", "isb", in(reg) address),
+            Access::Write => asm!("at s1e0w, {}", "isb", in(reg) address),
+        }
+    }
+    read_par_el1() & PAR_EL1_FAULT == 0
+}
+
+/// Returns the fixup address for a fault raised by a user access routine.
+pub fn search_exception_table(faulting_address: usize) -> Option<usize> {
+    let start = core::ptr::addr_of!(__ex_table_start);
+    let end = core::ptr::addr_of!(__ex_table_end);
+    let count = (end as usize - start as usize) / size_of::<ExceptionTableEntry>();
+    let table = unsafe { core::slice::from_raw_parts(start, count) };
+
+    // The kernel runs from the higher half, while the table holds link addresses.
+    let offset = faulting_address & KERNEL_VIRTUAL_MEM_SPACE;
+    table
+        .iter()
+        .find(|entry| entry.instruction == faulting_address & !KERNEL_VIRTUAL_MEM_SPACE)
+        .map(|entry| entry.fixup | offset)
+}
+
+/// PSTATE.PAN exists from ARMv8.1 on, the Cortex-A53 doesn't implement it.
+pub fn pan_supported() -> bool {
+    (read_id_aa64mmfr1_el1() >> 20) & 0xF != 0
+}
+
+/// Enables Privileged Access Never, if implemented.
+///
+/// `SCTLR_EL1.SPAN` is cleared, so every exception taken to EL1 sets PAN again.
+/// Without PAN the bit is RES1 and stays set, as in the boot configuration.
+pub fn initialize_pan() {
+    if !pan_supported() {
+        return;
+    }
+
+    unsafe {
+        asm!(
+            "mrs {tmp}, SCTLR_EL1",
+            "bic {tmp}, {tmp}, {span}",
+            "msr SCTLR_EL1, {tmp}",
+            "isb",
+            tmp = out(reg) _,
+            span = in(reg) SCTLR_EL1_SPAN,
+        );
+    }
+    set_pan(true);
+}
+
+fn pan_enabled() -> bool {
+    let pan: u64;
+    unsafe { asm!("mrs {}, S3_0_C4_C2_3", out(reg) pan) };
+    pan & (1 << 22) != 0
+}
+
+fn set_pan(enable: bool) {
+    unsafe {
+        if enable {
+            asm!(".inst {}", const MSR_PAN_1);
+        } else {
+            asm!(".inst {}", const MSR_PAN_0);
+        }
+    }
+}
@@ -0,0 +1,273 @@
+//! Clocks of the kernel.
+//!
+//! Instants of the monotonic clock are read from the system timer, which counts
+//! microseconds since it started and keeps running across all cores. Spans
+//! between them are [`Duration`]s.
+//!
+//! The Pi has no RTC, the wall clock is the monotonic clock shifted by an epoch
+//! offset, which is set once the calendar time is known. Until then it starts at
+//! the Unix epoch on boot, like Linux without RTC.
+
+use core::{
+    fmt,
+    ops::{Add, AddAssign, Sub, SubAssign},
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+};
+
+pub use core::time::Duration;
+
+use crate::pi3::timer::current_time_us;
+
+/// Point in time of the monotonic clock, with microsecond resolution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant {
+    micros: u64,
+}
+
+impl Instant {
+    /// Start of the clock, the time the system timer started.
+    pub const BOOT: Self = Self { micros: 0 };
+
+    pub fn now() -> Self {
+        Self {
+            micros: current_time_us(),
+        }
+    }
+
+    pub const fn from_micros(micros: u64) -> Self {
+        Self { micros }
+    }
+
+    /// Microseconds since [`Instant::BOOT`], the unit of the system timer.
+    pub const fn as_micros(self) -> u64 {
+        self.micros
+    }
+
+    /// Time since [`Instant::BOOT`].
+    pub const fn since_boot(self) -> Duration {
+        Duration::from_micros(self.micros)
+    }
+
+    /// Deadline `duration` from now.
+    pub fn after(duration: Duration) -> Self {
+        Self::now().saturating_add(duration)
+    }
+
+    pub fn elapsed(self) -> Duration {
+        Self::now().duration_since(self)
+    }
+
+    /// Time from `earlier` to `self`, zero if `earlier` is later.
+    pub fn duration_since(self, earlier: Self) -> Duration {
+        self.checked_duration_since(earlier).unwrap_or_default()
+    }
+
+    pub fn checked_duration_since(self, earlier: Self) -> Option<Duration> {
+        self.micros
+            .checked_sub(earlier.micros)
+            .map(Duration::from_micros)
+    }
+
+    /// Whether the deadline `self` has been reached.
+    pub fn has_passed(self) -> bool {
+        Self::now() >= self
+    }
+
+    /// Time left until the deadline `self`, zero if it has passed.
+    pub fn remaining(self) -> Duration {
+        self.duration_since(Self::now())
+    }
+
+    /// Adds `duration`, rounded up to whole microseconds so deadlines are never early.
+    pub fn checked_add(self, duration: Duration) -> Option<Self> {
+        self.micros
+            .checked_add(duration_to_micros_ceil(duration)?)
+            .map(Self::from_micros)
+    }
+
+    /// Subtracts `duration`, rounded up to whole microseconds.
+    pub fn checked_sub(self, duration: Duration) -> Option<Self> {
+        self.micros
+            .checked_sub(duration_to_micros_ceil(duration)?)
+            .map(Self::from_micros)
+    }
+
+    pub fn saturating_add(self, duration: Duration) -> Self {
+        self.checked_add(duration)
+            .unwrap_or(Self::from_micros(u64::MAX))
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    /// # Panics
+    ///
+    /// Panics if the result overflows, see [`Instant::checked_add`].
+    fn add(self, duration: Duration) -> Instant {
+        self.checked_add(duration)
+            .expect("overflow when adding duration to instant")
+    }
+}
+
+impl AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, duration: Duration) {
+        *self = *self + duration;
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Instant;
+
+    /// # Panics
+    ///
+    /// Panics if the result would be before [`Instant::BOOT`].
+    fn sub(self, duration: Duration) -> Instant {
+        self.checked_sub(duration)
+            .expect("overflow when subtracting duration from instant")
+    }
+}
+
+impl SubAssign<Duration> for Instant {
+    fn sub_assign(&mut self, duration: Duration) {
+        *self = *self - duration;
+    }
+}
+
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    /// Saturates to zero like [`Instant::duration_since`].
+    fn sub(self, earlier: Instant) -> Duration {
+        self.duration_since(earlier)
+    }
+}
+
+/// Formats the time since boot as seconds, like `12.345678`.
+///
+/// A width pads the seconds, so `{:>12}` aligns the decimal point.
+impl fmt::Display for Instant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = f.width().map_or(0, |width| width.saturating_sub(7));
+        write!(
+            f,
+            "{:>width$}.{:06}",
+            self.micros / 1_000_000,
+            self.micros % 1_000_000,
+        )
+    }
+}
+
+/// Time since the system timer started.
+pub fn uptime() -> Duration {
+    Instant::now().since_boot()
+}
+
+fn duration_to_micros_ceil(duration: Duration) -> Option<u64> {
+    u64::try_from(duration.as_nanos().div_ceil(1_000)).ok()
+}
+
+/// Unix time in microseconds at [`Instant::BOOT`].
+static EPOCH_OFFSET_US: AtomicU64 = AtomicU64::new(0);
+/// Whether the wall clock has been set since boot.
+static WALL_CLOCK_SET: AtomicBool = AtomicBool::new(false);
+
+/// Sets the wall clock to `since_epoch`, the time since the Unix epoch.
+///
+/// Times before boot, which can't be represented, are clamped to boot.
+pub fn set_wall_clock(since_epoch: Duration) {
+    let now = Instant::now().as_micros();
+    let since_epoch = u64::try_from(since_epoch.as_micros()).unwrap_or(u64::MAX);
+    EPOCH_OFFSET_US.store(since_epoch.saturating_sub(now), Ordering::Relaxed);
+    WALL_CLOCK_SET.store(true, Ordering::Relaxed);
+}
+
+/// Whether the wall clock has been set, otherwise it counts from the epoch on boot.
+pub fn wall_clock_is_set() -> bool {
+    WALL_CLOCK_SET.load(Ordering::Relaxed)
+}
+
+/// Time since the Unix epoch, according to the wall clock.
+pub fn wall_clock() -> Duration {
+    let offset = EPOCH_OFFSET_US.load(Ordering::Relaxed);
+    Duration::from_micros(offset.saturating_add(Instant::now().as_micros()))
+}
+
+/// Calendar date and time in UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: i64,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub microsecond: u32,
+}
+
+impl DateTime {
+    /// Date and time `since_epoch` after the Unix epoch.
+    pub fn from_unix(since_epoch: Duration) -> Self {
+        let seconds = since_epoch.as_secs();
+        let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+        let seconds_of_day = seconds % 86_400;
+
+        Self {
+            year,
+            month,
+            day,
+            hour: (seconds_of_day / 3600) as u8,
+            minute: (seconds_of_day / 60 % 60) as u8,
+            second: (seconds_of_day % 60) as u8,
+            microsecond: since_epoch.subsec_micros(),
+        }
+    }
+
+    /// Current date and time of the wall clock.
+    pub fn now() -> Self {
+        Self::from_unix(wall_clock())
+    }
+}
+
+/// Formats like `2024-05-17 13:37:00`, the precision selects the decimals of the second.
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )?;
+        match f.precision() {
+            Some(0) | None => Ok(()),
+            Some(precision) => {
+                let precision = precision.min(6);
+                let fraction = self.microsecond / 10u32.pow(6 - precision as u32);
+                write!(f, ".{:0precision$}", fraction)
+            }
+        }
+    }
+}
+
+/// Year, month and day of the `days`th day after the Unix epoch.
+///
+/// Counts in eras of 400 years starting at March 1st, so leap days are last
+/// in a year, see Howard Hinnant's `civil_from_days`.
+fn civil_from_days(days: i64) -> (i64, u8, u8) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u8;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u8;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
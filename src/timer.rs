@@ -0,0 +1,156 @@
+//! Kernel timer service, running callbacks at a later time.
+//!
+//! Timers are kept in a min-heap ordered by their deadline. Compare register 1
+//! of the system timer is armed for the earliest deadline, the callbacks of
+//! expired timers are run from its IRQ.
+
+use alloc::{collections::binary_heap::BinaryHeap, vec::Vec};
+use core::cmp::{Ordering, Reverse};
+
+use crate::{
+    interrupt_handlers::irq::{enable_irq_source, register_interrupt_handler, IRQSource},
+    pi3::timer::{current_time_us, set_compare, CompareChannel},
+    sync::IrqSpinLock,
+};
+
+pub type TimerId = u64;
+
+/// Compare register used by the service.
+const CHANNEL: CompareChannel = CompareChannel::C1;
+
+struct Timer {
+    deadline_us: u64,
+    id: TimerId,
+    /// Interval of a periodic timer, which is rescheduled after each expiry.
+    period_us: Option<u64>,
+    callback: fn(),
+}
+
+impl PartialEq for Timer {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Timer {}
+
+impl PartialOrd for Timer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Timer {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Timers with the same deadline expire in the order they were created.
+        (self.deadline_us, self.id).cmp(&(other.deadline_us, other.id))
+    }
+}
+
+struct TimerQueue {
+    timers: BinaryHeap<Reverse<Timer>>,
+    next_id: TimerId,
+}
+
+impl TimerQueue {
+    const fn new() -> Self {
+        Self {
+            timers: BinaryHeap::new(),
+            next_id: 1,
+        }
+    }
+
+    fn insert(&mut self, deadline_us: u64, period_us: Option<u64>, callback: fn()) -> TimerId {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.timers.push(Reverse(Timer {
+            deadline_us,
+            id,
+            period_us,
+            callback,
+        }));
+        self.arm();
+        id
+    }
+
+    /// Removes the timers, whose deadline has passed at `now`.
+    ///
+    /// Periodic timers are scheduled again, one period after their last deadline.
+    fn expire(&mut self, now: u64) -> Vec<fn()> {
+        let mut callbacks = Vec::new();
+        while self
+            .timers
+            .peek()
+            .is_some_and(|Reverse(timer)| timer.deadline_us <= now)
+        {
+            let Some(Reverse(mut timer)) = self.timers.pop() else {
+                break;
+            };
+            callbacks.push(timer.callback);
+
+            if let Some(period) = timer.period_us {
+                // Skip periods, which have been missed entirely.
+                timer.deadline_us = (timer.deadline_us + period).max(now + 1);
+                self.timers.push(Reverse(timer));
+            }
+        }
+        self.arm();
+        callbacks
+    }
+
+    fn arm(&self) {
+        if let Some(Reverse(timer)) = self.timers.peek() {
+            set_compare(CHANNEL, timer.deadline_us);
+        }
+    }
+}
+
+static TIMERS: IrqSpinLock<TimerQueue> = IrqSpinLock::new(TimerQueue::new());
+
+pub fn initialize_timer_service() {
+    register_interrupt_handler(IRQSource::SystemTimer1, handle_timer_interrupt);
+    enable_irq_source(IRQSource::SystemTimer1);
+}
+
+/// Runs `callback` once the system timer reaches `deadline_us`.
+pub fn schedule_at(deadline_us: u64, callback: fn()) -> TimerId {
+    TIMERS.lock().insert(deadline_us, None, callback)
+}
+
+/// Runs `callback` once, after `delay_us` microseconds.
+pub fn schedule_once(delay_us: u64, callback: fn()) -> TimerId {
+    schedule_at(current_time_us().saturating_add(delay_us), callback)
+}
+
+/// Runs `callback` every `period_us` microseconds, until the timer is cancelled.
+pub fn schedule_periodic(period_us: u64, callback: fn()) -> TimerId {
+    let period_us = period_us.max(1);
+    TIMERS.lock().insert(
+        current_time_us().saturating_add(period_us),
+        Some(period_us),
+        callback,
+    )
+}
+
+/// Removes the timer `id`, returns `false` if it already expired.
+pub fn cancel(id: TimerId) -> bool {
+    let mut queue = TIMERS.lock();
+    let count = queue.timers.len();
+    queue.timers.retain(|Reverse(timer)| timer.id != id);
+    count != queue.timers.len()
+}
+
+fn handle_timer_interrupt() {
+    // Callbacks run without the lock, so they may schedule timers themselves.
+    // Timers, which expired while they ran, are handled right away.
+    loop {
+        let callbacks = TIMERS.lock().expire(current_time_us());
+        if callbacks.is_empty() {
+            break;
+        }
+        for callback in callbacks {
+            callback();
+        }
+    }
+}
@@ -1,5 +1,8 @@
+set -e
+
 cd "$(dirname "$0")"
-cd ".."
 
 cargo build --target aarch64-unknown-none
+# Fails the build, if the symbol table doesn't fit its reservation.
+./embed_symbols.sh ../target/aarch64-unknown-none/debug/nova
 llvm-objcopy -O binary ../target/aarch64-unknown-none/debug/nova ../target/aarch64-unknown-none/debug/kernel8.img
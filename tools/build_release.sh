@@ -1,4 +1,5 @@
 cd "$(dirname "$0")"
 
 cargo build --target aarch64-unknown-none --release
+./embed_symbols.sh ../target/aarch64-unknown-none/release/nova
 llvm-objcopy -O binary ../target/aarch64-unknown-none/release/nova ../target/aarch64-unknown-none/release/kernel8.img
@@ -17,6 +17,10 @@ REMOTE_DIR="$TFTP_PATH"
 echo "[*] Building kernel..."
 cargo build --release --target aarch64-unknown-none
 
+# EMBED SYMBOLS
+echo "[*] Embedding symbol table..."
+./embed_symbols.sh "../$BUILD_PATH/$BINARY_NAME"
+
 # CONVERT TO IMG
 echo "[*] Convert kernel elf to img..."
 llvm-objcopy -O binary "../$BUILD_PATH/$BINARY_NAME" ../$BUILD_PATH/kernel8.img
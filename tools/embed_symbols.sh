@@ -0,0 +1,20 @@
+#!/bin/bash
+# Writes the symbol table of the kernel ELF into its `.symbol_table` section,
+# which the panic handler uses to resolve backtrace addresses.
+set -e
+
+if [ -z "$1" ]; then
+    echo "usage: $0 <kernel elf>"
+    exit 1
+fi
+ELF="$(realpath "$1")"
+
+cd "$(dirname "$0")/.."
+
+SIZE=$(llvm-size -A "$ELF" | awk '$1 == ".symbol_table" { print $2 }')
+TABLE="$ELF.symbols"
+
+llvm-nm --defined-only --print-size --demangle "$ELF" \
+    | cargo run --quiet -p symbol_table --features std --bin generate_symbol_table -- "$SIZE" > "$TABLE"
+llvm-objcopy --update-section .symbol_table="$TABLE" "$ELF"
+rm "$TABLE"
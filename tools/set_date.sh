@@ -0,0 +1,10 @@
+#!/bin/bash
+# Sets the wall clock of NovaOS to the time of the host, via the console on the serial port.
+#
+# Usage: ./set_date.sh /dev/ttyUSB0
+
+set -e
+
+SERIAL="${1:?serial device not set}"
+
+printf 'date %s\r' "$(date -u +%s)" > "$SERIAL"
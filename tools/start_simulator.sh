@@ -3,6 +3,7 @@ set -e
 cargo build --target aarch64-unknown-none --release
 cd "$(dirname "$0")"
 
+./embed_symbols.sh ../target/aarch64-unknown-none/release/nova
 llvm-objcopy -O binary ../target/aarch64-unknown-none/release/nova ../target/aarch64-unknown-none/release/kernel8.img
 
 qemu-system-aarch64 \
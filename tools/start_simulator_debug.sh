@@ -4,6 +4,7 @@ cargo build --target aarch64-unknown-none
 
 cd "$(dirname "$0")"
 
+./embed_symbols.sh ../target/aarch64-unknown-none/debug/nova
 llvm-objcopy -O binary ../target/aarch64-unknown-none/debug/nova ../target/aarch64-unknown-none/debug/kernel8.img
 
 qemu-system-aarch64 \
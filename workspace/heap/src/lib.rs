@@ -181,6 +181,9 @@ unsafe impl GlobalAlloc for Heap {
 
 unsafe impl Sync for Heap {}
 
+// The heap owns the memory its pointers refer to.
+unsafe impl Send for Heap {}
+
 unsafe fn fits(size: usize, header: *mut HeapHeader) -> bool {
     unsafe { (*header).free && size <= (*header).size }
 }
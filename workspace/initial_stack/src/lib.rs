@@ -0,0 +1,175 @@
+#![cfg_attr(not(test), no_std)]
+
+//! Initial process stack as defined by the System V AArch64 ABI.
+//!
+//! ```text
+//! stack_top ->  random bytes (AT_RANDOM)
+//!               environment strings, NUL-terminated
+//!               argument strings, NUL-terminated
+//!               padding to 16 bytes
+//!               auxv[n] = (AT_NULL, 0)
+//!               ...
+//!               auxv[0]
+//!               NULL
+//!               envp[envc - 1] ... envp[0]
+//!               NULL
+//!               argv[argc - 1] ... argv[0]
+//! sp ->         argc
+//! ```
+
+use core::{mem::size_of, result::Result};
+
+use alloc::vec::Vec;
+use nova_error::NovaError;
+
+extern crate alloc;
+
+pub const AT_NULL: u64 = 0;
+pub const AT_PHDR: u64 = 3;
+pub const AT_PHNUM: u64 = 5;
+pub const AT_PAGESZ: u64 = 6;
+pub const AT_ENTRY: u64 = 9;
+pub const AT_RANDOM: u64 = 25;
+
+pub const RANDOM_BYTES: usize = 16;
+
+const WORD: usize = size_of::<u64>();
+const STACK_ALIGNMENT: usize = 16;
+
+/// Addresses of the tables written by [`build`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layout {
+    /// Initial stack pointer, pointing at `argc`.
+    pub stack_pointer: usize,
+    pub argc: usize,
+    pub argv: usize,
+    pub envp: usize,
+    pub auxv: usize,
+}
+
+/// Bytes [`build`] needs below a 16 byte aligned `stack_top`.
+pub fn required_size(args: &[&str], env: &[&str], auxv_count: usize) -> usize {
+    let strings = align_up(
+        RANDOM_BYTES + strings_size(args) + strings_size(env),
+        STACK_ALIGNMENT,
+    );
+    strings + align_up(table_words(args, env, auxv_count) * WORD, STACK_ALIGNMENT)
+}
+
+/// Writes the initial stack of a process into `buffer`.
+///
+/// `buffer` holds the memory right below `stack_top`, addresses written into it
+/// are relative to `stack_top`. `AT_RANDOM` and `AT_NULL` are appended to `auxv`.
+pub fn build(
+    buffer: &mut [u8],
+    stack_top: usize,
+    args: &[&str],
+    env: &[&str],
+    auxv: &[(u64, u64)],
+    random: &[u8; RANDOM_BYTES],
+) -> Result<Layout, NovaError> {
+    if !stack_top.is_multiple_of(STACK_ALIGNMENT) {
+        return Err(NovaError::Misalignment);
+    }
+
+    let size = required_size(args, env, auxv.len());
+    if buffer.len() < size {
+        return Err(NovaError::General("Initial stack exceeds buffer."));
+    }
+
+    let base = stack_top - buffer.len();
+    let mut stack = StackWriter {
+        buffer,
+        base,
+        cursor: stack_top,
+    };
+
+    let random_address = stack.push_bytes(random);
+    let env_addresses = stack.push_strings(env);
+    let arg_addresses = stack.push_strings(args);
+
+    let stack_pointer = stack_top - size;
+    stack.cursor = stack_pointer;
+
+    stack.write_word(args.len() as u64);
+    let argv = stack.cursor;
+    for address in arg_addresses {
+        stack.write_word(address as u64);
+    }
+    stack.write_word(0);
+
+    let envp = stack.cursor;
+    for address in env_addresses {
+        stack.write_word(address as u64);
+    }
+    stack.write_word(0);
+
+    let auxv_address = stack.cursor;
+    for &(key, value) in auxv {
+        stack.write_word(key);
+        stack.write_word(value);
+    }
+    stack.write_word(AT_RANDOM);
+    stack.write_word(random_address as u64);
+    stack.write_word(AT_NULL);
+    stack.write_word(0);
+
+    Ok(Layout {
+        stack_pointer,
+        argc: args.len(),
+        argv,
+        envp,
+        auxv: auxv_address,
+    })
+}
+
+struct StackWriter<'a> {
+    buffer: &'a mut [u8],
+    /// Address of `buffer[0]`.
+    base: usize,
+    cursor: usize,
+}
+
+impl StackWriter<'_> {
+    /// Pushes `bytes` downwards and returns their address.
+    fn push_bytes(&mut self, bytes: &[u8]) -> usize {
+        self.cursor -= bytes.len();
+        let offset = self.cursor - self.base;
+        self.buffer[offset..offset + bytes.len()].copy_from_slice(bytes);
+        self.cursor
+    }
+
+    /// Pushes the strings NUL-terminated and returns their addresses.
+    fn push_strings(&mut self, strings: &[&str]) -> Vec<usize> {
+        strings
+            .iter()
+            .map(|string| {
+                self.push_bytes(&[0]);
+                self.push_bytes(string.as_bytes())
+            })
+            .collect()
+    }
+
+    /// Writes a word upwards, starting at the cursor.
+    fn write_word(&mut self, value: u64) {
+        let offset = self.cursor - self.base;
+        self.buffer[offset..offset + WORD].copy_from_slice(&value.to_le_bytes());
+        self.cursor += WORD;
+    }
+}
+
+fn strings_size(strings: &[&str]) -> usize {
+    strings.iter().map(|string| string.len() + 1).sum()
+}
+
+fn table_words(args: &[&str], env: &[&str], auxv_count: usize) -> usize {
+    // argc, argv + NULL, envp + NULL, auxv + AT_RANDOM + AT_NULL
+    1 + (args.len() + 1) + (env.len() + 1) + 2 * (auxv_count + 2)
+}
+
+fn align_up(value: usize, align: usize) -> usize {
+    (value + align - 1) & !(align - 1)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,153 @@
+use super::*;
+extern crate std;
+
+use std::{string::String, vec};
+
+const STACK_TOP: usize = 0x7F_FFFF_FFF0;
+const RANDOM: [u8; RANDOM_BYTES] = [0xA5; RANDOM_BYTES];
+
+/// Stack image together with the address of its first byte.
+struct Image {
+    buffer: std::vec::Vec<u8>,
+    base: usize,
+}
+
+impl Image {
+    fn new(size: usize) -> Self {
+        Self {
+            buffer: vec![0; size],
+            base: STACK_TOP - size,
+        }
+    }
+
+    fn word(&self, address: usize) -> u64 {
+        let offset = address - self.base;
+        u64::from_le_bytes(self.buffer[offset..offset + WORD].try_into().unwrap())
+    }
+
+    fn string(&self, address: usize) -> String {
+        let offset = address - self.base;
+        let len = self.buffer[offset..].iter().position(|&b| b == 0).unwrap();
+        String::from_utf8(self.buffer[offset..offset + len].to_vec()).unwrap()
+    }
+
+    fn build(&mut self, args: &[&str], env: &[&str], auxv: &[(u64, u64)]) -> Layout {
+        build(&mut self.buffer, STACK_TOP, args, env, auxv, &RANDOM).unwrap()
+    }
+}
+
+#[test]
+fn test_argc_and_argv() {
+    let args = ["app", "42", "--verbose"];
+    let mut image = Image::new(required_size(&args, &[], 0));
+    let layout = image.build(&args, &[], &[]);
+
+    assert_eq!(image.word(layout.stack_pointer), 3);
+    assert_eq!(layout.argc, 3);
+    assert_eq!(layout.argv, layout.stack_pointer + WORD);
+
+    // argv is in order and NULL-terminated
+    for (i, arg) in args.iter().enumerate() {
+        let pointer = image.word(layout.argv + i * WORD) as usize;
+        assert_eq!(image.string(pointer), *arg);
+    }
+    assert_eq!(image.word(layout.argv + args.len() * WORD), 0);
+}
+
+#[test]
+fn test_envp_follows_argv() {
+    let args = ["app"];
+    let env = ["TERM=vt100", "HOME=/"];
+    let mut image = Image::new(required_size(&args, &env, 0));
+    let layout = image.build(&args, &env, &[]);
+
+    assert_eq!(layout.envp, layout.argv + (args.len() + 1) * WORD);
+    for (i, variable) in env.iter().enumerate() {
+        let pointer = image.word(layout.envp + i * WORD) as usize;
+        assert_eq!(image.string(pointer), *variable);
+    }
+    assert_eq!(image.word(layout.envp + env.len() * WORD), 0);
+}
+
+#[test]
+fn test_auxiliary_vector() {
+    let auxv = [(AT_PAGESZ, 4096), (AT_ENTRY, 0x8_1234)];
+    let mut image = Image::new(required_size(&[], &[], auxv.len()));
+    let layout = image.build(&[], &[], &auxv);
+
+    assert_eq!(layout.auxv, layout.envp + WORD);
+
+    let entry = |i: usize| {
+        (
+            image.word(layout.auxv + 2 * i * WORD),
+            image.word(layout.auxv + (2 * i + 1) * WORD),
+        )
+    };
+
+    assert_eq!(entry(0), (AT_PAGESZ, 4096));
+    assert_eq!(entry(1), (AT_ENTRY, 0x8_1234));
+
+    // AT_RANDOM points to the random bytes at the top of the stack
+    let (key, random_address) = entry(2);
+    assert_eq!(key, AT_RANDOM);
+    let offset = random_address as usize - image.base;
+    assert_eq!(image.buffer[offset..offset + RANDOM_BYTES], RANDOM);
+    assert_eq!(random_address as usize + RANDOM_BYTES, STACK_TOP);
+
+    assert_eq!(entry(3), (AT_NULL, 0));
+}
+
+#[test]
+fn test_strings_are_nul_terminated() {
+    let args = ["a", "bc"];
+    let mut image = Image::new(required_size(&args, &[], 0));
+    let layout = image.build(&args, &[], &[]);
+
+    for (i, arg) in args.iter().enumerate() {
+        let pointer = image.word(layout.argv + i * WORD) as usize;
+        let offset = pointer - image.base;
+        assert_eq!(&image.buffer[offset..offset + arg.len()], arg.as_bytes());
+        assert_eq!(image.buffer[offset + arg.len()], 0);
+    }
+}
+
+#[test]
+fn test_stack_pointer_alignment() {
+    for count in 0..8 {
+        let args = vec!["x"; count];
+        let size = required_size(&args, &["A=1"], 1);
+        let mut image = Image::new(size);
+        let layout = image.build(&args, &["A=1"], &[(AT_PAGESZ, 4096)]);
+
+        assert_eq!(layout.stack_pointer % STACK_ALIGNMENT, 0);
+        assert_eq!(layout.stack_pointer, STACK_TOP - size);
+    }
+}
+
+#[test]
+fn test_tables_below_strings() {
+    let args = ["first", "second"];
+    let env = ["KEY=value"];
+    let mut image = Image::new(required_size(&args, &env, 1));
+    let layout = image.build(&args, &env, &[(AT_PAGESZ, 4096)]);
+
+    let table_end = layout.auxv + 3 * 2 * WORD;
+    let lowest_string = image.word(layout.argv + WORD) as usize;
+    assert!(table_end <= lowest_string);
+}
+
+#[test]
+fn test_buffer_too_small() {
+    let args = ["app"];
+    let mut buffer = vec![0; required_size(&args, &[], 0) - 1];
+    assert!(build(&mut buffer, STACK_TOP, &args, &[], &[], &RANDOM).is_err());
+}
+
+#[test]
+fn test_misaligned_stack_top() {
+    let mut buffer = vec![0; required_size(&[], &[], 0)];
+    assert!(matches!(
+        build(&mut buffer, STACK_TOP - 8, &[], &[], &[], &RANDOM),
+        Err(NovaError::Misalignment)
+    ));
+}
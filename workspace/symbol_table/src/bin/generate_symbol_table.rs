@@ -0,0 +1,82 @@
+//! Builds the symbol table embedded into the kernel image.
+//!
+//! Reads the output of `llvm-nm --defined-only --print-size --demangle` from
+//! stdin and writes the table, padded to `<size>` bytes, to stdout.
+//!
+//! ```text
+//! llvm-nm --defined-only --print-size --demangle nova | generate_symbol_table 262144 > symbols.bin
+//! ```
+
+use std::{
+    env,
+    io::{self, Read, Write},
+    process::ExitCode,
+};
+
+use symbol_table::encode;
+
+fn main() -> ExitCode {
+    let Some(size) = env::args()
+        .nth(1)
+        .and_then(|size| size.parse::<usize>().ok())
+    else {
+        eprintln!("usage: generate_symbol_table <size> < nm-output > table");
+        return ExitCode::FAILURE;
+    };
+
+    let mut input = String::new();
+    if let Err(err) = io::stdin().read_to_string(&mut input) {
+        eprintln!("failed to read symbols: {err}");
+        return ExitCode::FAILURE;
+    }
+
+    let symbols: Vec<(u64, u32, String)> = input.lines().filter_map(parse_line).collect();
+    let mut table = encode(&symbols);
+
+    if table.len() > size {
+        eprintln!(
+            "symbol table needs {} bytes, but only {} are reserved, \
+             rebuild with a larger NOVA_SYMBOL_TABLE_SIZE",
+            table.len(),
+            size
+        );
+        return ExitCode::FAILURE;
+    }
+    table.resize(size, 0);
+
+    if let Err(err) = io::stdout().write_all(&table) {
+        eprintln!("failed to write table: {err}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+/// Parses `address [size] type name`, keeping only functions.
+fn parse_line(line: &str) -> Option<(u64, u32, String)> {
+    let mut fields = line.split_whitespace();
+    let address = u64::from_str_radix(fields.next()?, 16).ok()?;
+
+    let mut field = fields.next()?;
+    let mut size = 0;
+    if field.len() > 1 {
+        size = u32::from_str_radix(field, 16).ok()?;
+        field = fields.next()?;
+    }
+
+    if !matches!(field, "t" | "T" | "W") {
+        return None;
+    }
+
+    let name = fields.collect::<Vec<_>>().join(" ");
+    Some((address, size, strip_hash(&name).to_string()))
+}
+
+/// Removes the `::h0123456789abcdef` suffix of legacy mangled Rust symbols.
+fn strip_hash(name: &str) -> &str {
+    match name.rsplit_once("::h") {
+        Some((path, hash)) if hash.len() == 16 && hash.chars().all(|c| c.is_ascii_hexdigit()) => {
+            path
+        }
+        _ => name,
+    }
+}
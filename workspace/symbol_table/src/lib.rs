@@ -0,0 +1,161 @@
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
+
+//! Compact address to name table, embedded into the kernel image after linking.
+//!
+//! ```text
+//! header:  magic "NSYM", entry count (u32)
+//! entries: address (u64), size (u32), name offset (u32), sorted by address
+//! names:   length (u16), UTF-8 bytes
+//! ```
+//!
+//! All values are little-endian, name offsets are relative to the end of the entries.
+
+use core::{fmt, mem::size_of};
+
+use alloc::{string::String, vec::Vec};
+
+extern crate alloc;
+
+pub const MAGIC: [u8; 4] = *b"NSYM";
+
+const HEADER_SIZE: usize = MAGIC.len() + size_of::<u32>();
+const ENTRY_SIZE: usize = size_of::<u64>() + 2 * size_of::<u32>();
+
+/// A function, resolved from an address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Symbol<'a> {
+    pub name: &'a str,
+    /// Distance of the address from the start of the function.
+    pub offset: usize,
+}
+
+impl fmt::Display for Symbol<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}+{:#x}", self.name, self.offset)
+    }
+}
+
+/// Read only view on an encoded table.
+#[derive(Clone, Copy)]
+pub struct SymbolTable<'a> {
+    entries: &'a [u8],
+    names: &'a [u8],
+}
+
+impl<'a> SymbolTable<'a> {
+    /// Parses an encoded table, trailing padding is ignored.
+    ///
+    /// Returns `None` if `data` doesn't hold a table.
+    pub fn parse(data: &'a [u8]) -> Option<Self> {
+        if data.get(..MAGIC.len())? != MAGIC {
+            return None;
+        }
+
+        let count = read_u32(data, MAGIC.len())? as usize;
+        let names_start = HEADER_SIZE.checked_add(count.checked_mul(ENTRY_SIZE)?)?;
+
+        Some(Self {
+            entries: data.get(HEADER_SIZE..names_start)?,
+            names: data.get(names_start..)?,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len() / ENTRY_SIZE
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Resolves `address` to the function containing it.
+    pub fn lookup(&self, address: u64) -> Option<Symbol<'a>> {
+        // Index of the first entry starting behind `address`.
+        let (mut low, mut high) = (0, self.len());
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if self.entry(middle)?.0 <= address {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+
+        let (start, size, name_offset) = self.entry(low.checked_sub(1)?)?;
+        let offset = address - start;
+        if size != 0 && offset >= size as u64 {
+            return None;
+        }
+
+        Some(Symbol {
+            name: self.name(name_offset as usize)?,
+            offset: offset as usize,
+        })
+    }
+
+    fn entry(&self, index: usize) -> Option<(u64, u32, u32)> {
+        let base = index * ENTRY_SIZE;
+        Some((
+            read_u64(self.entries, base)?,
+            read_u32(self.entries, base + size_of::<u64>())?,
+            read_u32(self.entries, base + size_of::<u64>() + size_of::<u32>())?,
+        ))
+    }
+
+    fn name(&self, offset: usize) -> Option<&'a str> {
+        let length = u16::from_le_bytes(self.names.get(offset..offset + 2)?.try_into().ok()?);
+        let start = offset + size_of::<u16>();
+        core::str::from_utf8(self.names.get(start..start + length as usize)?).ok()
+    }
+}
+
+/// Encodes `symbols` as `(address, size, name)`, names are truncated to `u16::MAX` bytes.
+pub fn encode(symbols: &[(u64, u32, String)]) -> Vec<u8> {
+    let mut sorted: Vec<&(u64, u32, String)> = symbols.iter().collect();
+    sorted.sort_by_key(|(address, _, _)| *address);
+    sorted.dedup_by_key(|(address, _, _)| *address);
+
+    let mut entries = Vec::with_capacity(HEADER_SIZE + sorted.len() * ENTRY_SIZE);
+    let mut names = Vec::new();
+
+    entries.extend_from_slice(&MAGIC);
+    entries.extend_from_slice(&(sorted.len() as u32).to_le_bytes());
+
+    for (address, size, name) in sorted {
+        let name = truncate(name, u16::MAX as usize);
+
+        entries.extend_from_slice(&address.to_le_bytes());
+        entries.extend_from_slice(&size.to_le_bytes());
+        entries.extend_from_slice(&(names.len() as u32).to_le_bytes());
+
+        names.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        names.extend_from_slice(name.as_bytes());
+    }
+
+    entries.extend_from_slice(&names);
+    entries
+}
+
+/// Shortens `name` to at most `max` bytes, without splitting a character.
+fn truncate(name: &str, max: usize) -> &str {
+    let mut end = name.len().min(max);
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    &name[..end]
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests;
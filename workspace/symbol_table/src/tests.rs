@@ -0,0 +1,101 @@
+use super::*;
+extern crate std;
+
+use std::{string::ToString, vec};
+
+fn table() -> Vec<u8> {
+    encode(&[
+        (0x8_2000, 0x40, "nova::kernel_loop".to_string()),
+        (0x8_0000, 0x100, "_start".to_string()),
+        (0x8_1000, 0, "vector_table".to_string()),
+    ])
+}
+
+#[test]
+fn test_lookup_function_start() {
+    let data = table();
+    let table = SymbolTable::parse(&data).unwrap();
+
+    assert_eq!(table.len(), 3);
+    assert_eq!(
+        table.lookup(0x8_0000),
+        Some(Symbol {
+            name: "_start",
+            offset: 0
+        })
+    );
+}
+
+#[test]
+fn test_lookup_inside_function() {
+    let data = table();
+    let table = SymbolTable::parse(&data).unwrap();
+
+    let symbol = table.lookup(0x8_2024).unwrap();
+    assert_eq!(symbol.name, "nova::kernel_loop");
+    assert_eq!(symbol.offset, 0x24);
+    assert_eq!(symbol.to_string(), "nova::kernel_loop+0x24");
+}
+
+#[test]
+fn test_lookup_outside_functions() {
+    let data = table();
+    let table = SymbolTable::parse(&data).unwrap();
+
+    // Below the first symbol
+    assert_eq!(table.lookup(0x7_FFFF), None);
+    // Behind the end of a sized symbol
+    assert_eq!(table.lookup(0x8_0100), None);
+    assert_eq!(table.lookup(0x8_2040), None);
+}
+
+#[test]
+fn test_lookup_unsized_symbol() {
+    let data = table();
+    let table = SymbolTable::parse(&data).unwrap();
+
+    let symbol = table.lookup(0x8_1800).unwrap();
+    assert_eq!(symbol.name, "vector_table");
+    assert_eq!(symbol.offset, 0x800);
+}
+
+#[test]
+fn test_trailing_padding_is_ignored() {
+    let mut data = table();
+    data.resize(data.len() + 256, 0);
+    let table = SymbolTable::parse(&data).unwrap();
+
+    assert_eq!(table.lookup(0x8_2000).unwrap().name, "nova::kernel_loop");
+}
+
+#[test]
+fn test_duplicate_addresses() {
+    let data = encode(&[
+        (0x1000, 8, "first".to_string()),
+        (0x1000, 8, "alias".to_string()),
+    ]);
+    let table = SymbolTable::parse(&data).unwrap();
+
+    assert_eq!(table.len(), 1);
+}
+
+#[test]
+fn test_invalid_table() {
+    assert!(SymbolTable::parse(&[]).is_none());
+    assert!(SymbolTable::parse(&[0; 64]).is_none());
+
+    // More entries announced than present
+    let mut data = vec![];
+    data.extend_from_slice(&MAGIC);
+    data.extend_from_slice(&10u32.to_le_bytes());
+    assert!(SymbolTable::parse(&data).is_none());
+}
+
+#[test]
+fn test_empty_table() {
+    let data = encode(&[]);
+    let table = SymbolTable::parse(&data).unwrap();
+
+    assert!(table.is_empty());
+    assert_eq!(table.lookup(0x8_0000), None);
+}